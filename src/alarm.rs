@@ -0,0 +1,106 @@
+use nannou::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+
+// Alarm levels below this are treated as no alarm at all
+const ALARM_THRESHOLD: f32 = 0.05;
+
+// How alarms spread through the flock
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Alarms {
+    // The number of steps before a boid reacts to an alarmed neighbour
+    pub delay: u32,
+    // The fraction of the alarm level lost every step
+    pub decay: f32,
+    // How much faster a fully alarmed boid can fly, as a fraction of its max speed
+    pub boost: f32,
+    // Every startle gets its own id, so each boid only joins each wave once
+    last_id: u32,
+}
+
+impl Alarms {
+    // The id for a new wave of alarm
+    pub fn next_id(&mut self) -> u32 {
+        self.last_id += 1;
+        self.last_id
+    }
+}
+
+impl Default for Alarms {
+    fn default() -> Self {
+        Self {
+            delay: 4,
+            decay: 0.01,
+            boost: 0.6,
+            last_id: 0,
+        }
+    }
+}
+
+// An alarm a boid has noticed, but hasn't reacted to yet
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+struct PendingAlarm {
+    level: f32,
+    id: u32,
+    heading: Vec2,
+    delay: u32,
+}
+
+// The alarm state of a single boid
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+pub struct AlarmState {
+    pub level: f32,
+    // The wave this boid was last part of
+    pub id: u32,
+    // The direction the boid flees in
+    pub heading: Vec2,
+    pending: Option<PendingAlarm>,
+}
+
+impl AlarmState {
+    // Fully alarms the boid, fleeing in the given direction
+    pub fn startle(&mut self, id: u32, heading: Vec2) {
+        self.level = 1.0;
+        self.id = id;
+        self.heading = heading.normalize_or_zero();
+        self.pending = None;
+    }
+
+    pub fn is_alarmed(&self) -> bool {
+        self.level > 0.0
+    }
+
+    // Whether a neighbour could pass this alarm on
+    pub fn is_spreading(&self) -> bool {
+        self.level > ALARM_THRESHOLD
+    }
+
+    // Moves the alarm one step forward - the alarm fades, a pending alarm might go off, and an
+    // alarm from the most alarmed neighbour might be noticed
+    pub fn step(&mut self, alarms: &Alarms, strongest_neighbour: Option<&Self>) {
+        self.level *= 1.0 - alarms.decay;
+        if self.level < ALARM_THRESHOLD {
+            self.level = 0.0;
+        }
+
+        if let Some(pending) = &mut self.pending {
+            if pending.delay == 0 {
+                self.level = pending.level;
+                self.id = pending.id;
+                self.heading = pending.heading;
+                self.pending = None;
+            } else {
+                pending.delay -= 1;
+            }
+        } else if let Some(neighbour) = strongest_neighbour {
+            // Only join a wave once, otherwise the alarm would bounce back and forth forever
+            if neighbour.is_spreading() && neighbour.id != self.id {
+                self.pending = Some(PendingAlarm {
+                    level: neighbour.level,
+                    id: neighbour.id,
+                    heading: neighbour.heading,
+                    delay: alarms.delay,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use crate::cli::ArenaKind;
+use crate::random::random_range;
+
+use clap::ValueEnum;
+use nannou::image::{
+    self,
+    error::{ParameterError, ParameterErrorKind},
+};
+use nannou::prelude::{Draw, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// How much of the world the circle and polygon arenas fill
+const ARENA_SCALE: f32 = 0.95;
+// The number of tries to find a random point inside the arena, before giving up
+const MAX_SPAWN_TRIES: usize = 1000;
+// The number of corners of the circle arena, where it has to be drawn as a polygon
+const CIRCLE_RESOLUTION: usize = 64;
+
+// What happens to boids at the edges of the rectangle arena - every other arena has walls
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Boundary {
+    // Boids leaving on one side come back on the other
+    Wrap,
+    // Boids bounce off the edges like walls
+    Bounce,
+}
+
+// The shape of the part of the world the boids can fly in
+#[derive(Clone)]
+pub enum Arena {
+    // The whole world, where boids wrap around the edges, unless they are set to bounce
+    Rect,
+    // The largest circle that fits in the world
+    Circle,
+    // A polygon, in units where the largest square that fits in the world goes from -1.0 to 1.0
+    Polygon(Vec<Vec2>),
+    // A bitmap stretched over the world
+    Mask(Mask),
+}
+
+impl Arena {
+    // A star, for when no other polygon is given
+    pub fn star() -> Self {
+        let points = (0..10)
+            .map(|i| {
+                let angle = i as f32 / 10.0 * std::f32::consts::TAU;
+                let radius = if i % 2 == 0 { 1.0 } else { 0.5 };
+                Vec2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+        Self::Polygon(points)
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Rect => "rectangle",
+            Self::Circle => "circle",
+            Self::Polygon(_) => "polygon",
+            Self::Mask(_) => "mask",
+        }
+    }
+
+    pub const fn kind(&self) -> ArenaKind {
+        match self {
+            Self::Rect => ArenaKind::Rect,
+            Self::Circle => ArenaKind::Circle,
+            Self::Polygon(_) => ArenaKind::Polygon,
+            Self::Mask(_) => ArenaKind::Mask,
+        }
+    }
+
+    // Whether the boids wrap around the edges, instead of bouncing off them
+    pub const fn wraps(&self) -> bool {
+        matches!(self, Self::Rect)
+    }
+
+    // The centre and radius of the circle arena
+    fn circle(bounds: Rect) -> (Vec2, f32) {
+        (bounds.xy(), bounds.w().min(bounds.h()) / 2.0 * ARENA_SCALE)
+    }
+
+    // The points of the polygon arena, in world coordinates
+    fn polygon_points(points: &[Vec2], bounds: Rect) -> Vec<Vec2> {
+        let scale = bounds.w().min(bounds.h()) / 2.0 * ARENA_SCALE;
+        points
+            .iter()
+            .map(|&point| bounds.xy() + point * scale)
+            .collect()
+    }
+
+    pub fn contains(&self, point: Vec2, bounds: Rect) -> bool {
+        match self {
+            Self::Rect => bounds.contains(point),
+            Self::Circle => {
+                let (centre, radius) = Self::circle(bounds);
+                point.distance(centre) < radius
+            }
+            Self::Polygon(points) => polygon_contains(&Self::polygon_points(points, bounds), point),
+            Self::Mask(mask) => mask.is_open(point, bounds),
+        }
+    }
+
+    // A random point inside the arena - if none is found, one known to be inside it
+    pub fn random_point(&self, bounds: Rect) -> Vec2 {
+        (0..MAX_SPAWN_TRIES)
+            .map(|_| {
+                Vec2::new(
+                    random_range(bounds.left(), bounds.right()),
+                    random_range(bounds.bottom(), bounds.top()),
+                )
+            })
+            .find(|&point| self.contains(point, bounds))
+            .unwrap_or_else(|| match self {
+                // Masks can be mostly walls, and their centre can be one
+                Self::Mask(mask) => mask.random_open_point(bounds),
+                _ => bounds.xy(),
+            })
+    }
+
+    // Keeps a boid that has left the arena inside it, by bouncing it off the wall - previous is
+    // where it was before it moved. Returns the new position and velocity, or nothing if the boid
+    // is still inside
+    pub fn bounce(
+        &self,
+        previous: Vec2,
+        position: Vec2,
+        velocity: Vec2,
+        radius: f32,
+        bounds: Rect,
+    ) -> Option<(Vec2, Vec2)> {
+        match self {
+            // Only used when the rect doesn't wrap around
+            Self::Rect => {
+                let inside = Rect::from_xy_wh(bounds.xy(), bounds.wh() - radius * 2.0);
+                if inside.contains(position) {
+                    return None;
+                }
+                // Turn around along the axes the boid went out on
+                let mut velocity = velocity;
+                if position.x < inside.left() || position.x > inside.right() {
+                    velocity.x = -velocity.x;
+                }
+                if position.y < inside.bottom() || position.y > inside.top() {
+                    velocity.y = -velocity.y;
+                }
+                let position = position.max(inside.bottom_left()).min(inside.top_right());
+                Some((position, velocity))
+            }
+            Self::Circle => {
+                let (centre, arena_radius) = Self::circle(bounds);
+                let offset = position - centre;
+                if offset.length() < arena_radius - radius {
+                    return None;
+                }
+                let normal = -offset.normalize_or_zero();
+                Some((
+                    centre - normal * (arena_radius - radius),
+                    reflect(velocity, normal),
+                ))
+            }
+            Self::Polygon(points) => {
+                let points = Self::polygon_points(points, bounds);
+                let (closest, distance) = closest_point_on_polygon(&points, position);
+                let inside = polygon_contains(&points, position);
+                if inside && distance >= radius {
+                    return None;
+                }
+                // The normal points from the wall into the arena
+                let towards = (position - closest).normalize_or_zero();
+                let normal = if inside { towards } else { -towards };
+                Some((closest + normal * radius, reflect(velocity, normal)))
+            }
+            Self::Mask(mask) => {
+                if mask.is_open(position, bounds) {
+                    return None;
+                }
+                // Go back to where the boid was, and turn around along the blocked axes
+                let mut velocity = velocity;
+                if !mask.is_open(Vec2::new(position.x, previous.y), bounds) {
+                    velocity.x = -velocity.x;
+                }
+                if !mask.is_open(Vec2::new(previous.x, position.y), bounds) {
+                    velocity.y = -velocity.y;
+                }
+                Some((previous, velocity))
+            }
+        }
+    }
+
+    // Draws the walls of the arena
+    pub fn show(&self, draw: &Draw, bounds: Rect) {
+        match self {
+            Self::Rect => {
+                draw.rect()
+                    .xy(bounds.xy())
+                    .wh(bounds.wh())
+                    .no_fill()
+                    .stroke_weight(2.0)
+                    .stroke(nannou::color::rgba(1.0, 1.0, 1.0, 0.3));
+            }
+            Self::Circle => {
+                let (centre, radius) = Self::circle(bounds);
+                draw.ellipse()
+                    .xy(centre)
+                    .radius(radius)
+                    .no_fill()
+                    .stroke_weight(2.0)
+                    .stroke(nannou::color::rgba(1.0, 1.0, 1.0, 0.3));
+            }
+            Self::Polygon(points) => {
+                draw.polyline()
+                    .weight(2.0)
+                    .points_closed(Self::polygon_points(points, bounds))
+                    .rgba(1.0, 1.0, 1.0, 0.3);
+            }
+            Self::Mask(mask) => mask.show(draw, bounds),
+        }
+    }
+
+    // The walls of the arena as a closed outline, for drawing without nannou - the mask has
+    // no outline, only wall_rects
+    pub fn outline(&self, bounds: Rect) -> Option<Vec<Vec2>> {
+        match self {
+            Self::Rect => Some(vec![
+                bounds.bottom_left(),
+                bounds.bottom_right(),
+                bounds.top_right(),
+                bounds.top_left(),
+            ]),
+            Self::Circle => {
+                let (centre, radius) = Self::circle(bounds);
+                let points = (0..CIRCLE_RESOLUTION)
+                    .map(|i| {
+                        let angle = i as f32 / CIRCLE_RESOLUTION as f32 * std::f32::consts::TAU;
+                        centre + Vec2::new(angle.cos(), angle.sin()) * radius
+                    })
+                    .collect();
+                Some(points)
+            }
+            Self::Polygon(points) => Some(Self::polygon_points(points, bounds)),
+            Self::Mask(_) => None,
+        }
+    }
+
+    // The filled parts of the mask arena - the other arenas only have an outline
+    pub fn wall_rects(&self, bounds: Rect) -> Vec<Rect> {
+        match self {
+            Self::Mask(mask) => mask.wall_rects(bounds),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Mirrors a velocity moving into a wall, so it moves away from it instead
+fn reflect(velocity: Vec2, normal: Vec2) -> Vec2 {
+    let into_wall = velocity.dot(normal);
+    if into_wall < 0.0 {
+        velocity - 2.0 * into_wall * normal
+    } else {
+        velocity
+    }
+}
+
+// Whether a point is inside a polygon, by counting how many edges a ray from it crosses
+fn polygon_contains(points: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (i, &a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// The point on the edges of a polygon closest to a point, and how far away it is
+fn closest_point_on_polygon(points: &[Vec2], point: Vec2) -> (Vec2, f32) {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &a)| {
+            let b = points[(i + 1) % points.len()];
+            let edge = b - a;
+            let t =
+                ((point - a).dot(edge) / edge.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+            let closest = a + edge * t;
+            (closest, closest.distance(point))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((point, 0.0))
+}
+
+// A bitmap of walls, loaded from an image where black pixels are walls
+#[derive(Clone)]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    // Row by row, from the top of the image
+    walls: Vec<bool>,
+    // The indices of the pixels that aren't walls, to place things in when random points miss
+    open: Vec<usize>,
+}
+
+impl Mask {
+    pub fn load(path: &Path) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.to_luma8();
+        let walls: Vec<bool> = image.pixels().map(|pixel| pixel.0[0] < 128).collect();
+        let open: Vec<usize> = (0..walls.len()).filter(|&i| !walls[i]).collect();
+        if open.is_empty() {
+            return Err(image::ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::Generic(String::from(
+                    "the mask is all walls - white pixels are where the boids can fly",
+                )),
+            )));
+        }
+        Ok(Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            walls,
+            open,
+        })
+    }
+
+    // The centre of a random pixel that isn't a wall
+    fn random_open_point(&self, bounds: Rect) -> Vec2 {
+        let index = self.open[random_range(0, self.open.len())];
+        let (x, y) = (index % self.width, index / self.width);
+        Vec2::new(
+            bounds.left() + (x as f32 + 0.5) / self.width as f32 * bounds.w(),
+            bounds.top() - (y as f32 + 0.5) / self.height as f32 * bounds.h(),
+        )
+    }
+
+    // The pixel under a point in the world, if the point is inside the world
+    fn pixel_of(&self, point: Vec2, bounds: Rect) -> Option<(usize, usize)> {
+        if !bounds.contains(point) {
+            return None;
+        }
+        let x = (point.x - bounds.left()) / bounds.w() * self.width as f32;
+        // Images start at the top, the world starts at the bottom
+        let y = (bounds.top() - point.y) / bounds.h() * self.height as f32;
+        Some((
+            (x as usize).min(self.width - 1),
+            (y as usize).min(self.height - 1),
+        ))
+    }
+
+    // Whether a point is inside the world and not in a wall
+    pub fn is_open(&self, point: Vec2, bounds: Rect) -> bool {
+        self.pixel_of(point, bounds)
+            .is_some_and(|(x, y)| !self.walls[y * self.width + x])
+    }
+
+    // Draws the walls
+    fn show(&self, draw: &Draw, bounds: Rect) {
+        for rect in self.wall_rects(bounds) {
+            draw.rect()
+                .xy(rect.xy())
+                .wh(rect.wh())
+                .rgba(1.0, 1.0, 1.0, 0.15);
+        }
+    }
+
+    // The walls in world coordinates, merging each run of wall pixels in a row into one rect
+    fn wall_rects(&self, bounds: Rect) -> Vec<Rect> {
+        let pixel = Vec2::new(
+            bounds.w() / self.width as f32,
+            bounds.h() / self.height as f32,
+        );
+        let mut rects = Vec::new();
+        for (y, row) in self.walls.chunks(self.width).enumerate() {
+            let mut x = 0;
+            while x < self.width {
+                if !row[x] {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < self.width && row[x] {
+                    x += 1;
+                }
+                let run = (x - start) as f32;
+                rects.push(Rect::from_x_y_w_h(
+                    bounds.left() + (start as f32 + run / 2.0) * pixel.x,
+                    bounds.top() - (y as f32 + 0.5) * pixel.y,
+                    run * pixel.x,
+                    pixel.y,
+                ));
+            }
+        }
+        rects
+    }
+}
@@ -1,10 +1,47 @@
-use crate::color::Color;
+use crate::alarm::{AlarmState, Alarms};
+use crate::color::{hue, Color, ColorScheme};
+use crate::ecology::{mutate, random_offset};
+use crate::falloff::Falloff;
+use crate::food::Food;
+use crate::glyph::{Glyph, Glyphs, Shape};
 use crate::math;
-use nannou::prelude::{random_range, Draw, Point2, Rect, Vec2, Vec2Angle};
+use crate::random::random_range;
+use crate::steering::{Contributions, Rule};
+use crate::trail::Trails;
+use crate::world::World;
+use nannou::prelude::{Draw, Rect, Vec2, Vec2Angle, Vec2Rotate};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// The size of a default boid, which has a mass of 1.0
+const DEFAULT_DIAMETER: f32 = 10.0;
+// How much more neighbours that have found food count in alignment and cohesion
+const FOOD_SIGNAL_WEIGHT: f32 = 4.0;
+// How much longer than they really are the debug vectors are drawn
+const VELOCITY_SCALE: f32 = 5.0;
+const FORCE_SCALE: f32 = 1000.0;
+// The number of neighbours that gets the last color of the density scheme
+const DENSITY_SATURATION: f32 = 20.0;
+// The color alarmed boids fade towards
+const ALARM_COLOR: Color = Color::new(1.0, 0.1, 0.3, 1.0);
+
+// Every boid gets its own id, so it can be found again after the flock changes
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// The id the next boid gets
+pub fn next_id() -> u64 {
+    NEXT_ID.load(Ordering::Relaxed)
+}
+
+// Carries on giving out ids from a saved flock, so new boids don't share ids with it
+pub fn set_next_id(id: u64) {
+    NEXT_ID.store(id, Ordering::Relaxed);
+}
 
 // So we can compare boids using ==
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
 pub struct Boid {
+    id: u64,
     position: Vec2,
     velocity: Vec2,
     acceleration: Vec2,
@@ -12,12 +49,78 @@ pub struct Boid {
     max_force: f32,
     color: Color,
     diameter: f32,
+    // Forces get divided by the mass, so heavier boids turn more sluggishly
+    mass: f32,
     // How far the boid can "see"
     perception_radius: f32,
     // These three modifiers get applied to the alignment etc. to scale it
     alignment_mod: f32,
     cohesion_mod: f32,
     separation_mod: f32,
+    // How much neighbours count in each of the three rules, depending on their distance
+    alignment_falloff: Falloff,
+    cohesion_falloff: Falloff,
+    separation_falloff: Falloff,
+    // How strongly the boid steers towards food it can see
+    foraging_mod: f32,
+    // How strongly the boid steers up the pheromone gradient - negative values steer down it
+    pheromone_mod: f32,
+    // Whether the boid could see food last step - neighbours follow it more closely
+    senses_food: bool,
+    // Alarmed boids flee, and pass the alarm on to their neighbours
+    alarm: AlarmState,
+    // Only used when the ecology is enabled - the boid dies when it runs out of energy, or gets
+    // older than its lifespan
+    energy: f32,
+    age: u32,
+    lifespan: u32,
+    // The forces from the last step, before they were reset - they are worked out again every
+    // step, so they aren't saved
+    #[serde(skip)]
+    contributions: Contributions,
+    // Which kind of boid this is - only used for colors and shapes
+    species: u8,
+    // The smallest id in the group of connected boids this boid is part of
+    cluster: u64,
+}
+
+// The tunable parameters of a boid, as saved in presets
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Parameters {
+    pub max_speed: f32,
+    pub max_force: f32,
+    pub perception_radius: f32,
+    pub alignment_mod: f32,
+    pub cohesion_mod: f32,
+    pub separation_mod: f32,
+    // Older presets don't have the falloffs, so they get the defaults
+    #[serde(default)]
+    pub alignment_falloff: Falloff,
+    #[serde(default)]
+    pub cohesion_falloff: Falloff,
+    #[serde(default = "default_separation_falloff")]
+    pub separation_falloff: Falloff,
+}
+
+// Dividing the direction by the distance - the same as the difference divided by the distance squared
+const fn default_separation_falloff() -> Falloff {
+    Falloff::Inverse
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            max_speed: 5.0,
+            max_force: 0.025,
+            perception_radius: 100.0,
+            alignment_mod: 0.8,
+            cohesion_mod: 0.8,
+            separation_mod: 0.9,
+            alignment_falloff: Falloff::Constant,
+            cohesion_falloff: Falloff::Constant,
+            separation_falloff: default_separation_falloff(),
+        }
+    }
 }
 
 impl Boid {
@@ -32,25 +135,58 @@ impl Boid {
     }
 
     // Shows the boid to the screen, as a triangle, pointing in the same direction as the boid
-    pub fn show(&self, draw: &Draw) {
-        draw.tri()
-            .xy(self.position)
-            // A triangle pointing to the right - so it has an angle of zero degrees
-            // Basically looks like this, where the `o` is the origin:
-            //
-            //     *
-            //      o *  --->  |>
-            //     *
-            //
-            .points(
-                Point2::new(self.radius(), 0.0),
-                Point2::new(-self.radius(), -self.radius()),
-                Point2::new(-self.radius(), self.radius()),
-            )
-            .w_h(self.diameter, self.diameter)
+    pub fn show(&self, draw: &Draw, trails: &Trails, glyphs: &Glyphs) {
+        // The trail goes behind the triangle
+        if trails.enabled {
+            self.show_trail(draw, trails);
+        }
+        let glyph = glyphs.of(self.species);
+        if let (Glyph::Sprite, Some(sprite)) = (glyph, &glyphs.sprite) {
             // Set its angle to the boids velocity angle - where the boid is facing
-            .rotate(self.velocity.angle())
-            .rgba(self.color.r, self.color.g, self.color.b, self.color.a);
+            draw.texture(sprite)
+                .xy(self.position)
+                .w_h(self.diameter, self.diameter)
+                .rotate(self.velocity.angle());
+            return;
+        }
+        let color = nannou::color::rgba(self.color.r, self.color.g, self.color.b, self.color.a);
+        for shape in self.outline(glyph) {
+            match shape {
+                Shape::Fill(points) => {
+                    draw.polygon().points(points).color(color);
+                }
+                Shape::Stroke(points) => {
+                    draw.polyline().weight(1.0).points(points).color(color);
+                }
+            }
+        }
+    }
+
+    // The glyph of the boid, turned to where it is facing and moved to where it is
+    pub fn outline(&self, glyph: Glyph) -> Vec<Shape> {
+        let angle = self.velocity.angle();
+        glyph
+            .shapes(self.radius(), self.velocity.length() / self.max_speed)
+            .into_iter()
+            .map(|shape| shape.map(|point| point.rotate(angle) + self.position))
+            .collect()
+    }
+
+    // Draws the recent positions of the boid as a line, which fades the older it gets
+    fn show_trail(&self, draw: &Draw, trails: &Trails) {
+        let length = trails.length.max(2);
+        let mut age = 0;
+        for segment in trails.segments(self, length) {
+            let points = segment.into_iter().map(|point| {
+                age += 1;
+                let alpha = trails.alpha * age as f32 / length as f32;
+                (
+                    point,
+                    nannou::color::rgba(self.color.r, self.color.g, self.color.b, alpha),
+                )
+            });
+            draw.polyline().weight(1.0).points_colored(points);
+        }
     }
 
     // Draws a transparent circle at the boids position, with a radius equal to the boids
@@ -65,39 +201,175 @@ impl Boid {
             .rgba(1.0, 1.0, 1.0, alpha);
     }
 
+    // Draws a ring around the boid, to make it stand out
+    pub fn show_highlight(&self, draw: &Draw) {
+        draw.ellipse()
+            .xy(self.position)
+            .radius(self.diameter)
+            .no_fill()
+            .stroke_weight(1.5)
+            .stroke(nannou::color::rgba(1.0, 1.0, 1.0, 0.8));
+    }
+
+    // Draws the velocity, and the forces of the three rules from the last step, as arrows
+    // White is velocity, blue is alignment, green is cohesion and red is separation
+    pub fn show_forces(&self, draw: &Draw) {
+        for (vector, color) in self.force_arrows() {
+            // Zero length arrows can't be drawn
+            if vector.length() < f32::EPSILON {
+                continue;
+            }
+            draw.arrow()
+                .start(self.position)
+                .end(self.position + vector)
+                .weight(1.0)
+                .head_length(3.0)
+                .head_width(2.0)
+                .rgba(color.r, color.g, color.b, color.a);
+        }
+    }
+
+    // The velocity and the forces of the three rules, scaled so they can be seen, and their colors
+    pub fn force_arrows(&self) -> [(Vec2, Color); 4] {
+        [
+            (
+                self.velocity * VELOCITY_SCALE,
+                Color::new(1.0, 1.0, 1.0, 0.8),
+            ),
+            (
+                self.contributions.alignment * FORCE_SCALE,
+                Color::new(0.3, 0.5, 1.0, 0.8),
+            ),
+            (
+                self.contributions.cohesion * FORCE_SCALE,
+                Color::new(0.3, 1.0, 0.4, 0.8),
+            ),
+            (
+                self.contributions.separation * FORCE_SCALE,
+                Color::new(1.0, 0.3, 0.3, 0.8),
+            ),
+        ]
+    }
+
+    // Draws a line to every boid this boid counts as a neighbour
+    pub fn show_neighbour_lines(&self, draw: &Draw, flock: &[Self]) {
+        for other in flock {
+            if self.is_neighbour(other) {
+                draw.line()
+                    .start(self.position)
+                    .end(other.position)
+                    .weight(1.0)
+                    .rgba(1.0, 1.0, 1.0, 0.3);
+            }
+        }
+    }
+
     /* The three rules */
     // The main flocking function - calls the three rules, and updates the boids with color and
     // movement
-    pub fn flock(&mut self, flock: &[Self], win_rect: Rect) {
+    pub fn flock(&mut self, flock: &[Self], world: &World) {
         // Changed from &Vec<Boid> to &[Boid], from vector type to slice type
-        // The three rules
-        let alignment = self.align(flock) * self.alignment_mod;
-        let cohesion = self.cohere(flock) * self.cohesion_mod;
-        let separation = self.separate(flock) * self.separation_mod;
-
-        // Updating the acceleration
-        self.acceleration += alignment;
-        self.acceleration += cohesion;
-        self.acceleration += separation;
+        // The three rules - the neighbours are counted even when alignment is left out
+        let (alignment, neighbours, local_alignment) = self.align(flock);
+        let alignment = if world.follows(Rule::Alignment) {
+            alignment * self.alignment_mod
+        } else {
+            Vec2::ZERO
+        };
+        let cohesion = if world.follows(Rule::Cohesion) {
+            self.cohere(flock) * self.cohesion_mod
+        } else {
+            Vec2::ZERO
+        };
+        let separation = if world.follows(Rule::Separation) {
+            self.separate(flock) * self.separation_mod
+        } else {
+            Vec2::ZERO
+        };
+        // And steering towards food, which isn't one of the three, but is close enough
+        let foraging = self.forage(&world.foraging.food);
+        self.senses_food = foraging.is_some();
+        let foraging = if world.follows(Rule::Foraging) {
+            foraging.unwrap_or(Vec2::ZERO) * self.foraging_mod
+        } else {
+            Vec2::ZERO
+        };
+        // Following the trails left by other boids
+        let trailing = if world.pheromones.enabled && world.follows(Rule::Trailing) {
+            self.follow_gradient(world.pheromones.gradient(self.position)) * self.pheromone_mod
+        } else {
+            Vec2::ZERO
+        };
+        // Catching the alarm from neighbours, and fleeing if alarmed
+        let fleeing = self.react_to_alarm(flock, &world.alarms);
+        let fleeing = if world.follows(Rule::Fleeing) {
+            fleeing
+        } else {
+            Vec2::ZERO
+        };
+
+        // Updating the acceleration - the forces are listed from the most to the least important
+        let total = world.steering.combine(
+            &[fleeing, separation, alignment, cohesion, foraging, trailing],
+            self.max_force,
+        );
+        self.acceleration += total;
+        // Remember what went into it
+        self.contributions = Contributions {
+            alignment,
+            cohesion,
+            separation,
+            foraging,
+            trailing,
+            fleeing,
+            total,
+            neighbours,
+            local_alignment,
+        };
 
         // Update velocity and position - and resetting acceleration
-        self.update(win_rect);
+        self.update(world);
         // Update colors based on pos, vel, and acc - updating after self.update() is important
 
-        self.update_color(win_rect);
+        self.update_color(world);
     }
 
     // Updating the position and velocity of the boid
-    fn update(&mut self, boundary_rect: Rect) {
+    fn update(&mut self, world: &World) {
+        let previous = self.position;
         self.position += self.velocity;
-        self.velocity += self.acceleration;
+        // a = F / m
+        self.velocity += self.acceleration / self.mass;
 
-        // Making the speed at most max_speed
-        self.velocity = self.velocity.clamp_length_max(self.max_speed);
+        // Making the speed at most max_speed - alarmed boids get to go a bit faster
+        self.velocity = self
+            .velocity
+            .clamp_length_max(self.speed_limit(&world.alarms));
 
         // Reset the acceleration
         self.acceleration = Vec2::ZERO;
 
+        self.keep_inside(world, previous);
+    }
+
+    // Keeps the boid in the world after it moved from previous - by bouncing it off the walls of
+    // arenas that don't wrap around, and wrapping it around the edges of those that do
+    pub fn keep_inside(&mut self, world: &World, previous: Vec2) {
+        let boundary_rect = world.bounds;
+        if !world.wraps() {
+            if let Some((position, velocity)) = world.arena.bounce(
+                previous,
+                self.position,
+                self.velocity,
+                self.radius(),
+                boundary_rect,
+            ) {
+                self.position = position;
+                self.velocity = velocity;
+            }
+            return;
+        }
+
         // Check if stuff is inside bounds
         if self.position.x < boundary_rect.left() + self.radius() {
             self.position.x = boundary_rect.right() - self.radius();
@@ -114,49 +386,64 @@ impl Boid {
     }
 
     // The three separate methods for the three rules
-    fn align(&self, flock: &[Self]) -> Vec2 {
+    // Alignment also returns the number of neighbours, and how aligned they are with this boid,
+    // since it has to look at all of them anyway
+    fn align(&self, flock: &[Self]) -> (Vec2, usize, f32) {
         // Compute the average steering
         let mut steering = Vec2::ZERO;
-        let mut total = 0;
+        let mut total = 0.0;
+        let mut neighbours = 0;
+        let mut headings = self.velocity.normalize_or_zero();
         // TODO: Parallelize this with rayon?
         for other in flock {
-            let distance = self.position.distance(other.position);
             // Only count the ones within perception_radius and the ones that arent itself
-            if distance < self.perception_radius && self != other {
-                steering += other.velocity;
-                total += 1;
+            if self.is_neighbour(other) {
+                let distance = self.position.distance(other.position);
+                let weight = other.signal_weight()
+                    * self
+                        .alignment_falloff
+                        .weight(distance, self.perception_radius);
+                steering += other.velocity * weight;
+                total += weight;
+                neighbours += 1;
+                headings += other.velocity.normalize_or_zero();
             }
         }
-        if total > 0 {
+        if total > 0.0 {
             // Divides the average by a vector with the values of the length of the part of flock within perception
             // The average steering
-            steering /= Vec2::new(total as f32, total as f32);
+            steering /= Vec2::new(total, total);
             // Set the length of the vector to the boids max speed
             steering = steering.clamp_length(self.max_speed, self.max_speed);
             steering -= self.velocity;
             // Only get affected by the other boids by a certain amount
             steering = steering.clamp_length_max(self.max_force);
         }
-        steering
+        let local_alignment = headings.length() / (neighbours + 1) as f32;
+        (steering, neighbours, local_alignment)
     }
 
     fn cohere(&self, flock: &[Self]) -> Vec2 {
         // Compute the average location
         let mut steering = Vec2::ZERO;
-        let mut total = 0;
+        let mut total = 0.0;
         for other in flock {
-            let distance = self.position.distance(other.position);
             // Only count the ones within perception_radius and the ones that arent itself
-            if distance < self.perception_radius && self != other {
-                steering += other.position;
-                total += 1;
+            if self.is_neighbour(other) {
+                let distance = self.position.distance(other.position);
+                let weight = other.signal_weight()
+                    * self
+                        .cohesion_falloff
+                        .weight(distance, self.perception_radius);
+                steering += other.position * weight;
+                total += weight;
             }
         }
 
         // Only change self if there is actually any boids nearby
-        if total > 0 {
+        if total > 0.0 {
             // Divides the average by a vector with the values of the length of the part of flock within perception
-            steering /= Vec2::new(total as f32, total as f32);
+            steering /= Vec2::new(total, total);
             steering -= self.position;
             // Set the length of the vector to the boids max speed
             steering = steering.clamp_length(self.max_speed, self.max_speed);
@@ -174,13 +461,17 @@ impl Boid {
 
         for other in flock {
             let distance = self.position.distance(other.position);
+            // The distance between the edges of the two boids, rather than their centres
+            let surface_distance = (distance - self.radius() - other.radius()).max(f32::EPSILON);
             // Only count the ones within perception_radius and the ones that arent itself
-            if distance < self.perception_radius && self != other {
-                let mut difference = self.position - other.position;
-                // Make the effect stronger the closer the boids are together
-                difference /= distance * distance;
-                // Add the difference between positions
-                steering += difference;
+            if surface_distance < self.perception_radius && distance > 0.0 && self.id != other.id {
+                let direction = (self.position - other.position) / distance;
+                // Weigh the direction by the surface distance, so big boids keep further apart
+                // By default this makes the effect stronger the closer the boids are together
+                steering += direction
+                    * self
+                        .separation_falloff
+                        .weight(surface_distance, self.perception_radius);
                 total += 1;
             }
         }
@@ -198,7 +489,116 @@ impl Boid {
         steering
     }
 
+    // Whether another boid is close enough to be seen, and isn't this boid
+    pub fn is_neighbour(&self, other: &Self) -> bool {
+        self.position.distance(other.position) < self.perception_radius && self.id != other.id
+    }
+
+    // Notices alarmed neighbours, and steers in the escape heading while alarmed
+    fn react_to_alarm(&mut self, flock: &[Self], alarms: &Alarms) -> Vec2 {
+        // The same neighbours as the ones used for alignment
+        let strongest = flock
+            .iter()
+            .filter(|other| self.is_neighbour(other))
+            .map(|other| other.alarm)
+            .filter(AlarmState::is_spreading)
+            .max_by(|a, b| a.level.total_cmp(&b.level));
+        self.alarm.step(alarms, strongest.as_ref());
+
+        if !self.alarm.is_alarmed() {
+            return Vec2::ZERO;
+        }
+        // Set the length of the vector to the boids boosted speed
+        let speed = self.speed_limit(alarms);
+        let mut steering = self.alarm.heading.clamp_length(speed, speed);
+        steering -= self.velocity;
+        // Panicking boids steer harder
+        steering.clamp_length_max(self.max_force * (1.0 + self.alarm.level))
+    }
+
+    // The max speed, boosted by the alarm level
+    fn speed_limit(&self, alarms: &Alarms) -> f32 {
+        self.max_speed * (1.0 + alarms.boost * self.alarm.level)
+    }
+
+    // Steers towards the closest patch of food within perception, if there is one
+    fn forage(&self, food: &[Food]) -> Option<Vec2> {
+        let closest = food
+            .iter()
+            .filter(|food| !food.is_empty())
+            // The distance to the edge of the patch
+            .map(|food| (food, self.position.distance(food.position) - food.radius))
+            .filter(|&(_, distance)| distance < self.perception_radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        // Set the length of the vector to the boids max speed
+        let mut steering =
+            (closest.0.position - self.position).clamp_length(self.max_speed, self.max_speed);
+        steering -= self.velocity;
+        // Only get affected by the food by a certain amount
+        Some(steering.clamp_length_max(self.max_force))
+    }
+
+    // Steers along the pheromone gradient, if there is one
+    fn follow_gradient(&self, gradient: Vec2) -> Vec2 {
+        if gradient.length() < f32::EPSILON {
+            return Vec2::ZERO;
+        }
+        // Set the length of the vector to the boids max speed
+        let mut steering = gradient.clamp_length(self.max_speed, self.max_speed);
+        steering -= self.velocity;
+        // Only get affected by the pheromones by a certain amount
+        steering.clamp_length_max(self.max_force)
+    }
+
+    // How much this boid counts in the alignment and cohesion of its neighbours
+    fn signal_weight(&self) -> f32 {
+        if self.senses_food {
+            FOOD_SIGNAL_WEIGHT
+        } else {
+            1.0
+        }
+    }
+
     // Functions for getting attributes //
+    // Returns the id of the boid
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
+    // Returns the forces the boid steered with in the last step
+    pub const fn contributions(&self) -> &Contributions {
+        &self.contributions
+    }
+
+    // Returns the position of the boid
+    pub const fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub const fn color(&self) -> Color {
+        self.color
+    }
+
+    // Returns the velocity of the boid
+    pub const fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
+
+    // Returns the tunable parameters of the boid
+    pub const fn parameters(&self) -> Parameters {
+        Parameters {
+            max_speed: self.max_speed,
+            max_force: self.max_force,
+            perception_radius: self.perception_radius,
+            alignment_mod: self.alignment_mod,
+            cohesion_mod: self.cohesion_mod,
+            separation_mod: self.separation_mod,
+            alignment_falloff: self.alignment_falloff,
+            cohesion_falloff: self.cohesion_falloff,
+            separation_falloff: self.separation_falloff,
+        }
+    }
+
     // Returns the perception radius of the boid
     pub const fn perception_radius(&self) -> f32 {
         self.perception_radius
@@ -214,6 +614,11 @@ impl Boid {
         self.diameter
     }
 
+    // Returns the mass of the boid
+    pub const fn mass(&self) -> f32 {
+        self.mass
+    }
+
     // Returns the max speed of the boid
     pub const fn max_speed(&self) -> f32 {
         self.max_speed
@@ -239,6 +644,46 @@ impl Boid {
         self.separation_mod
     }
 
+    // Returns the falloff of the alignment
+    pub const fn alignment_falloff(&self) -> Falloff {
+        self.alignment_falloff
+    }
+
+    // Returns the falloff of the cohesion
+    pub const fn cohesion_falloff(&self) -> Falloff {
+        self.cohesion_falloff
+    }
+
+    // Returns the falloff of the separation
+    pub const fn separation_falloff(&self) -> Falloff {
+        self.separation_falloff
+    }
+
+    // Returns the foraging modifier of the boid
+    pub const fn foraging_modifier(&self) -> f32 {
+        self.foraging_mod
+    }
+
+    // Returns the pheromone modifier of the boid
+    pub const fn pheromone_modifier(&self) -> f32 {
+        self.pheromone_mod
+    }
+
+    // Returns the energy of the boid
+    pub const fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    // Returns the age of the boid, in steps
+    pub const fn age(&self) -> u32 {
+        self.age
+    }
+
+    // Whether the boid still has energy left, and hasn't died of old age
+    pub fn is_alive(&self) -> bool {
+        self.energy > 0.0 && self.age < self.lifespan
+    }
+
     // Returns the radius of the boid
     pub fn radius(&self) -> f32 {
         self.diameter / 2.0
@@ -250,9 +695,25 @@ impl Boid {
         self.perception_radius *= multiplier;
     }
 
-    // Change the size of the boid
+    // Change the size of the boid - the mass follows the area of the boid
     pub fn change_diameter(&mut self, multiplier: f32) {
         self.diameter *= multiplier;
+        self.mass *= multiplier * multiplier;
+    }
+
+    // A copy of the boid, with its own id
+    pub fn duplicate(&self) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            ..*self
+        }
+    }
+
+    // Sets the size of the boid, with a mass relative to the area of a default boid
+    pub fn with_diameter(mut self, diameter: f32) -> Self {
+        self.mass = (diameter / DEFAULT_DIAMETER).powi(2);
+        self.diameter = diameter;
+        self
     }
 
     // Changes the max speed of the boid
@@ -280,6 +741,59 @@ impl Boid {
         self.separation_mod *= multiplier;
     }
 
+    // Sets all the tunable parameters of the boid at once
+    pub fn set_parameters(&mut self, parameters: &Parameters) {
+        self.max_speed = parameters.max_speed;
+        self.max_force = parameters.max_force;
+        self.perception_radius = parameters.perception_radius;
+        self.alignment_mod = parameters.alignment_mod;
+        self.cohesion_mod = parameters.cohesion_mod;
+        self.separation_mod = parameters.separation_mod;
+        self.alignment_falloff = parameters.alignment_falloff;
+        self.cohesion_falloff = parameters.cohesion_falloff;
+        self.separation_falloff = parameters.separation_falloff;
+    }
+
+    // Sets the falloff of the alignment
+    pub fn change_alignment_falloff(&mut self, falloff: Falloff) {
+        self.alignment_falloff = falloff;
+    }
+
+    // Sets the falloff of the cohesion
+    pub fn change_cohesion_falloff(&mut self, falloff: Falloff) {
+        self.cohesion_falloff = falloff;
+    }
+
+    // Sets the falloff of the separation
+    pub fn change_separation_falloff(&mut self, falloff: Falloff) {
+        self.separation_falloff = falloff;
+    }
+
+    // Makes the boid steer the other way along the pheromone gradient
+    pub fn flip_pheromone_modifier(&mut self) {
+        self.pheromone_mod = -self.pheromone_mod;
+    }
+
+    // Alarms the boid, making it flee in the given direction
+    pub fn startle(&mut self, id: u32, heading: Vec2) {
+        self.alarm.startle(id, heading);
+    }
+
+    // Returns the species of the boid
+    pub const fn species(&self) -> u8 {
+        self.species
+    }
+
+    // Sets the species of the boid
+    pub fn change_species(&mut self, species: u8) {
+        self.species = species;
+    }
+
+    // Sets the cluster the boid is part of
+    pub fn change_cluster(&mut self, cluster: u64) {
+        self.cluster = cluster;
+    }
+
     // Changes the position of the boid
     pub fn change_position(&mut self, new_position: Vec2) {
         self.position = new_position;
@@ -290,8 +804,65 @@ impl Boid {
         self.velocity = new_velocity;
     }
 
-    // Update the color of the boid, based on pos, vel and acc
-    fn update_color(&mut self, win_rect: Rect) {
+    // Functions for the ecology //
+    // Spends energy on being alive, moving and steering - and gets one step older
+    pub fn metabolise(&mut self, idle_cost: f32, speed_cost: f32, force_cost: f32) {
+        self.energy -= idle_cost
+            + speed_cost * self.velocity.length()
+            + force_cost * self.contributions.total.length();
+        self.age += 1;
+    }
+
+    // Gains energy from eating
+    pub fn feed(&mut self, energy: f32) {
+        self.energy += energy;
+    }
+
+    // Splits off a child, which gets half of the energy, and a mutated copy of the parameters
+    pub fn reproduce(&mut self, mutation: f32) -> Self {
+        self.energy /= 2.0;
+        Self {
+            position: self.position + random_offset(self.diameter),
+            velocity: -self.velocity,
+            max_speed: mutate(self.max_speed, mutation),
+            perception_radius: mutate(self.perception_radius, mutation),
+            alignment_mod: mutate(self.alignment_mod, mutation),
+            cohesion_mod: mutate(self.cohesion_mod, mutation),
+            separation_mod: mutate(self.separation_mod, mutation),
+            foraging_mod: mutate(self.foraging_mod, mutation),
+            pheromone_mod: mutate(self.pheromone_mod, mutation),
+            energy: self.energy,
+            age: 0,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            alarm: AlarmState::default(),
+            contributions: Contributions::default(),
+            ..*self
+        }
+    }
+
+    // Update the color of the boid, based on what the color scheme shows
+    fn update_color(&mut self, world: &World) {
+        let palette = world.coloring.palette;
+        self.color = match world.coloring.scheme {
+            ColorScheme::Classic => self.classic_color(world.bounds),
+            ColorScheme::HeadingHue => hue(self.velocity.angle() / std::f32::consts::TAU),
+            ColorScheme::Speed => palette.gradient(self.velocity.length() / self.max_speed),
+            ColorScheme::Density => {
+                palette.gradient(self.contributions.neighbours as f32 / DENSITY_SATURATION)
+            }
+            ColorScheme::Species => palette.category(self.species as usize),
+            ColorScheme::Cluster => palette.category(self.cluster as usize),
+            ColorScheme::LocalAlignment => palette.gradient(self.contributions.local_alignment),
+            ColorScheme::Flat => palette.gradient(0.8),
+        };
+        // Alarmed boids stand out, so the wave can be seen moving through the flock
+        if self.alarm.is_alarmed() {
+            self.color = self.color.lerp(ALARM_COLOR, self.alarm.level);
+        }
+    }
+
+    // The original color, based on pos and vel
+    fn classic_color(&self, win_rect: Rect) -> Color {
         // The lower and upper possible rgb values for the boids
         // Having them be != 0.0 or 1.0 means that there will be no fully black and no fully white
         // boids
@@ -301,7 +872,7 @@ impl Boid {
         // R - velocity
         // G - X-position
         // B - Y-position
-        self.color = Color::new(
+        Color::new(
             math::map(self.velocity.length(), 0.0, self.max_speed(), lower, upper),
             // Map left to right
             math::map(
@@ -320,25 +891,41 @@ impl Boid {
                 upper,
             ),
             1.0,
-        );
+        )
     }
 }
 
 // The default implementation for boids - the middle of the screen etc.
 impl Default for Boid {
     fn default() -> Self {
+        let parameters = Parameters::default();
         Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             position: Vec2::ZERO,
             velocity: Vec2::new(random_range(-0.1, 0.1), random_range(-0.1, 0.1)),
             acceleration: Vec2::ZERO,
-            max_speed: 5.0,
-            max_force: 0.025,
+            max_speed: parameters.max_speed,
+            max_force: parameters.max_force,
             color: Color::new(1.0, 1.0, 1.0, 1.0),
-            diameter: 10.0,
-            perception_radius: 100.0,
-            alignment_mod: 0.8,
-            cohesion_mod: 0.8,
-            separation_mod: 0.9,
+            diameter: DEFAULT_DIAMETER,
+            mass: 1.0,
+            perception_radius: parameters.perception_radius,
+            alignment_mod: parameters.alignment_mod,
+            cohesion_mod: parameters.cohesion_mod,
+            separation_mod: parameters.separation_mod,
+            alignment_falloff: parameters.alignment_falloff,
+            cohesion_falloff: parameters.cohesion_falloff,
+            separation_falloff: parameters.separation_falloff,
+            foraging_mod: 1.0,
+            pheromone_mod: 1.0,
+            senses_food: false,
+            energy: 100.0,
+            age: 0,
+            lifespan: 7200,
+            alarm: AlarmState::default(),
+            contributions: Contributions::default(),
+            species: 0,
+            cluster: 0,
         }
     }
 }
@@ -0,0 +1,69 @@
+use nannou::prelude::{Draw, Rect, Vec2};
+
+// How far in and out the camera can zoom
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 50.0;
+
+// A drag with the mouse, in window coordinates
+pub struct Drag {
+    pub start: Vec2,
+    pub last: Vec2,
+}
+
+// What part of the world is shown in the window
+pub struct Camera {
+    // The point in the world at the centre of the window
+    pub position: Vec2,
+    // The number of pixels per unit of the world
+    pub zoom: f32,
+}
+
+impl Camera {
+    // A camera showing the whole world in the window
+    pub fn fitting(world: Rect, window: Rect) -> Self {
+        let mut camera = Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+        };
+        camera.fit(world, window);
+        camera
+    }
+
+    // Centres the world, and zooms so all of it is visible
+    pub fn fit(&mut self, world: Rect, window: Rect) {
+        self.position = world.xy();
+        self.zoom = (window.w() / world.w())
+            .min(window.h() / world.h())
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    // The same view in a window of another size, showing at least as much as this one does
+    pub fn resized(&self, window: Rect, other: Rect) -> Self {
+        Self {
+            position: self.position,
+            zoom: self.zoom * (other.w() / window.w()).min(other.h() / window.h()),
+        }
+    }
+
+    // A draw which takes world coordinates, and draws them where the camera shows them
+    pub fn transform(&self, draw: &Draw) -> Draw {
+        draw.scale(self.zoom).translate(-self.position.extend(0.0))
+    }
+
+    // Converts a point in the window to a point in the world
+    pub fn to_world(&self, point: Vec2) -> Vec2 {
+        point / self.zoom + self.position
+    }
+
+    // Moves the camera, so that the world follows the mouse
+    pub fn pan(&mut self, window_delta: Vec2) {
+        self.position -= window_delta / self.zoom;
+    }
+
+    // Zooms by a factor, keeping the point under the mouse in place
+    pub fn zoom_at(&mut self, point: Vec2, factor: f32) {
+        let before = self.to_world(point);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.position += before - self.to_world(point);
+    }
+}
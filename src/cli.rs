@@ -0,0 +1,521 @@
+use crate::arena::Boundary;
+use crate::boid::Parameters;
+use crate::color::{ColorScheme, Palette};
+use crate::config::{load_config, Config};
+use crate::falloff::Falloff;
+use crate::glyph::Glyph;
+use crate::optimise::Fitness;
+use crate::record::Animation;
+use crate::steering::{Rule, SteeringMode};
+use crate::tui::TuiMarker;
+
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use nannou::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// The command line arguments - the doc comments are shown by --help
+#[derive(Parser)]
+#[command(version, about, allow_negative_numbers = true)]
+pub struct Cli {
+    /// A TOML or RON file with the world, the flock, the boids, the colors and the keys to start
+    /// with - the options given here win over it
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Start with the parameters of the first preset in this file
+    #[arg(long)]
+    pub preset: Option<PathBuf>,
+
+    /// Start with the preset of this name, like murmuration, fish-school, gnats or milling-torus
+    /// - press L to switch between presets
+    #[arg(long)]
+    pub start_preset: Option<String>,
+
+    /// The number of boids to start with
+    #[arg(long, default_value_t = 1024)]
+    pub boids: usize,
+
+    /// Start the random numbers from this, so a run can be repeated exactly [default: random]
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Start from a snapshot saved with F7 or --save-snapshot, instead of a new flock - it
+    /// brings its own random numbers, so --seed is left out
+    #[arg(long)]
+    pub snapshot: Option<PathBuf>,
+
+    /// The width of the window, in pixels
+    #[arg(long, default_value_t = 1024, value_parser = clap::value_parser!(u32).range(1..))]
+    pub window_width: u32,
+
+    /// The height of the window, in pixels
+    #[arg(long, default_value_t = 768, value_parser = clap::value_parser!(u32).range(1..))]
+    pub window_height: u32,
+
+    /// Fill the whole screen with the window
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Run the simulation without a window for --steps steps, print how the flock ended up, and
+    /// save it to --output if it is given
+    #[arg(long)]
+    pub headless: bool,
+
+    /// The number of steps to run with --headless
+    #[arg(long, default_value_t = 1000)]
+    pub steps: usize,
+
+    /// Where --headless saves the last step - an image the size of the window if it ends in
+    /// .png, or vector shapes if it ends in .svg
+    #[arg(long, value_parser = parse_output)]
+    pub output: Option<PathBuf>,
+
+    /// Where --headless saves a snapshot of the last step, to carry on from with --snapshot
+    #[arg(long)]
+    pub save_snapshot: Option<PathBuf>,
+
+    /// The width of the world, which doesn't change with the window
+    #[arg(long, default_value_t = 1600.0, value_parser = positive)]
+    pub world_width: f32,
+
+    /// The height of the world
+    #[arg(long, default_value_t = 900.0, value_parser = positive)]
+    pub world_height: f32,
+
+    /// What happens to boids at the edges of the rectangle arena - the other arenas always bounce
+    #[arg(long, value_enum, default_value_t = Boundary::Wrap)]
+    pub boundary: Boundary,
+
+    /// How the forces of the rules are combined - press P to switch
+    #[arg(long, value_enum, default_value_t = SteeringMode::WeightedSum)]
+    pub steering: SteeringMode,
+
+    /// Push overlapping boids apart - press K to toggle
+    #[arg(long)]
+    pub collisions: bool,
+
+    /// The rules the boids steer with, as a comma separated list - the rest are left out
+    /// [default: all of them]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub rules: Option<Vec<Rule>>,
+
+    /// The fastest a boid can fly, in pixels per step [default: 5]
+    #[arg(long, value_parser = positive)]
+    pub max_speed: Option<f32>,
+
+    /// The strongest a boid can steer [default: 0.025]
+    #[arg(long, value_parser = positive)]
+    pub max_force: Option<f32>,
+
+    /// How far a boid can see its neighbours, in pixels [default: 100]
+    #[arg(long, value_parser = positive)]
+    pub perception_radius: Option<f32>,
+
+    /// The weight of steering the same way as the neighbours [default: 0.8]
+    #[arg(long, value_parser = non_negative)]
+    pub alignment: Option<f32>,
+
+    /// The weight of steering towards the middle of the neighbours [default: 0.8]
+    #[arg(long, value_parser = non_negative)]
+    pub cohesion: Option<f32>,
+
+    /// The weight of steering away from neighbours that are too close [default: 0.9]
+    #[arg(long, value_parser = non_negative)]
+    pub separation: Option<f32>,
+
+    /// How much neighbours count in alignment, depending on how far away they are
+    /// [default: constant]
+    #[arg(long, value_enum)]
+    pub alignment_falloff: Option<Falloff>,
+
+    /// How much neighbours count in cohesion [default: constant]
+    #[arg(long, value_enum)]
+    pub cohesion_falloff: Option<Falloff>,
+
+    /// How much neighbours count in separation [default: inverse]
+    #[arg(long, value_enum)]
+    pub separation_falloff: Option<Falloff>,
+
+    /// The number of food patches placed by pressing F
+    #[arg(long, default_value_t = 12)]
+    pub food_sources: usize,
+
+    /// How much food grows back in each patch every step
+    #[arg(long, default_value_t = 0.5, value_parser = non_negative)]
+    pub food_regrowth: f32,
+
+    /// The number of steps before a boid reacts to an alarmed neighbour
+    #[arg(long, default_value_t = 4)]
+    pub alarm_delay: u32,
+
+    /// The fraction of the alarm level that fades every step
+    #[arg(long, default_value_t = 0.01, value_parser = fraction)]
+    pub alarm_decay: f32,
+
+    /// The number of species the flock is split into, shown by the species color scheme
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..))]
+    pub species: u8,
+
+    /// The arena to start in - press B to switch between them
+    #[arg(long, value_enum, default_value_t = ArenaKind::Rect)]
+    pub arena: ArenaKind,
+
+    /// The corners of the polygon arena, as x,y pairs from -1.0 to 1.0 [default: a star]
+    #[arg(long, num_args = 3.., value_parser = parse_point, allow_hyphen_values = true)]
+    pub arena_polygon: Option<Vec<Vec2>>,
+
+    /// An image to use as an arena, where black pixels are walls
+    #[arg(long)]
+    pub arena_mask: Option<PathBuf>,
+
+    /// What the colors of the boids show - press M to switch between them
+    #[arg(long, value_enum, default_value_t = ColorScheme::Classic)]
+    pub color_scheme: ColorScheme,
+
+    /// The colors the schemes use - press Shift + M to switch between them
+    #[arg(long, value_enum, default_value_t = Palette::Viridis)]
+    pub palette: Palette,
+
+    /// What the boids are drawn as - press G to switch between them
+    #[arg(long, value_enum, default_value_t = Glyph::Triangle)]
+    pub glyph: Glyph,
+
+    /// Draw each species as its own glyph, from this comma separated list
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub species_glyphs: Option<Vec<Glyph>>,
+
+    /// An image to draw the boids with, as the sprite glyph
+    #[arg(long)]
+    pub sprite: Option<PathBuf>,
+
+    /// The directory exported heatmaps are written to - press F3 to export them
+    #[arg(long, default_value = ".")]
+    pub export_dir: PathBuf,
+
+    /// The directory the quick-save slots are kept in - press F7 to save and F8 to load
+    #[arg(long, default_value = "snapshots")]
+    pub snapshot_dir: PathBuf,
+
+    /// How far, in pixels, the trails in SVG files may stray from where the boids really went,
+    /// so they need fewer points
+    #[arg(long, default_value_t = 1.0, value_parser = non_negative)]
+    pub svg_tolerance: f32,
+
+    /// The directory recorded frames are written to - press F4 to start and stop recording
+    #[arg(long, default_value = "recording")]
+    pub record_dir: PathBuf,
+
+    /// The width of recorded frames, in pixels
+    #[arg(long, default_value_t = 1280, value_parser = clap::value_parser!(u32).range(1..))]
+    pub record_width: u32,
+
+    /// The height of recorded frames, in pixels
+    #[arg(long, default_value_t = 720, value_parser = clap::value_parser!(u32).range(1..))]
+    pub record_height: u32,
+
+    /// Only record every nth step
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub record_every: u64,
+
+    /// Stop recording by itself after this many frames
+    #[arg(long)]
+    pub record_frames: Option<usize>,
+
+    /// How long each frame of the animation is shown, in milliseconds
+    #[arg(long, default_value_t = 33)]
+    pub frame_delay: u32,
+
+    /// What the recorded frames are put together into, once the recording stops
+    #[arg(long, value_enum, default_value_t = Animation::Gif)]
+    pub animation: Animation,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // The config file, whose values have already been filled in above
+    #[arg(skip)]
+    pub file_config: Config,
+
+    // The ids of the options given on the command line, which win over the config file, even when
+    // it is reloaded
+    #[arg(skip)]
+    pub given: HashSet<String>,
+}
+
+impl Cli {
+    // The options as they are when none are given
+    pub fn defaults() -> Self {
+        Self::parse_from(["boids-rs"])
+    }
+
+    // The parameters new flocks start with - the ones given on the command line, and the
+    // defaults for the rest
+    pub fn parameters(&self) -> Parameters {
+        let defaults = Parameters::default();
+        Parameters {
+            max_speed: self.max_speed.unwrap_or(defaults.max_speed),
+            max_force: self.max_force.unwrap_or(defaults.max_force),
+            perception_radius: self.perception_radius.unwrap_or(defaults.perception_radius),
+            alignment_mod: self.alignment.unwrap_or(defaults.alignment_mod),
+            cohesion_mod: self.cohesion.unwrap_or(defaults.cohesion_mod),
+            separation_mod: self.separation.unwrap_or(defaults.separation_mod),
+            alignment_falloff: self.alignment_falloff.unwrap_or(defaults.alignment_falloff),
+            cohesion_falloff: self.cohesion_falloff.unwrap_or(defaults.cohesion_falloff),
+            separation_falloff: self
+                .separation_falloff
+                .unwrap_or(defaults.separation_falloff),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArenaKind {
+    Rect,
+    Circle,
+    Polygon,
+    Mask,
+}
+
+// Parses a point written as x,y
+fn parse_point(text: &str) -> Result<Vec2, String> {
+    let (x, y) = text
+        .split_once(',')
+        .ok_or_else(|| format!("expected a point like 0.5,-0.5, got {text}"))?;
+    let parse = |number: &str| {
+        number
+            .trim()
+            .parse::<f32>()
+            .map_err(|error| format!("invalid coordinate {number}: {error}"))
+    };
+    Ok(Vec2::new(parse(x)?, parse(y)?))
+}
+
+// Parses a number, which has to be finite
+fn parse_number(text: &str) -> Result<f32, String> {
+    let number = text
+        .trim()
+        .parse::<f32>()
+        .map_err(|error| format!("invalid number {text}: {error}"))?;
+    if number.is_finite() {
+        Ok(number)
+    } else {
+        Err(format!("expected a finite number, got {text}"))
+    }
+}
+
+// Parses a number greater than zero
+fn positive(text: &str) -> Result<f32, String> {
+    let number = parse_number(text)?;
+    if number > 0.0 {
+        Ok(number)
+    } else {
+        Err(format!("expected a number greater than 0, got {text}"))
+    }
+}
+
+// Parses a number that is zero or more
+fn non_negative(text: &str) -> Result<f32, String> {
+    let number = parse_number(text)?;
+    if number >= 0.0 {
+        Ok(number)
+    } else {
+        Err(format!("expected a number of at least 0, got {text}"))
+    }
+}
+
+// Parses a number from zero to one
+fn fraction(text: &str) -> Result<f32, String> {
+    let number = parse_number(text)?;
+    if (0.0..=1.0).contains(&number) {
+        Ok(number)
+    } else {
+        Err(format!("expected a number from 0 to 1, got {text}"))
+    }
+}
+
+// Parses a path to save to, which has to be in a format that can be saved without a window
+fn parse_output(text: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(text);
+    match output_format(&path) {
+        Some(_) => Ok(path),
+        None => Err(format!(
+            "expected a path ending in .png or .svg, got {text}"
+        )),
+    }
+}
+
+// The format of a path to save to
+#[derive(PartialEq, Clone, Copy)]
+pub enum OutputFormat {
+    Png,
+    Svg,
+}
+
+pub fn output_format(path: &Path) -> Option<OutputFormat> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some(OutputFormat::Png),
+        "svg" => Some(OutputFormat::Svg),
+        _ => None,
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Evolve the flocking parameters without a window, and save the best ones as presets
+    Optimise(OptimiseArgs),
+    /// Run the simulation without a window, and save the last step as an image
+    Render(RenderArgs),
+    /// Run the simulation without a window, and record it like F4 does
+    Record(RecordArgs),
+    /// Run the simulation without a window, and save the last step as an SVG, like F5 does
+    Svg(SvgArgs),
+    /// Run the simulation in the terminal, with the same keys as the window - escape quits
+    Tui(TuiArgs),
+}
+
+#[derive(Args)]
+pub struct OptimiseArgs {
+    /// What makes a set of parameters good
+    #[arg(long, value_enum, default_value_t = Fitness::Polarisation)]
+    pub fitness: Fitness,
+    /// The value to aim for - a polarisation between 0 and 1, or a distance in pixels
+    #[arg(long)]
+    pub target: Option<f32>,
+    /// The number of generations to evolve
+    #[arg(long, default_value_t = 10)]
+    pub generations: usize,
+    /// The number of parameter sets in each generation
+    #[arg(long, default_value_t = 24)]
+    pub population: usize,
+    /// The number of boids in each simulation
+    #[arg(long, default_value_t = 128)]
+    pub boids: usize,
+    /// The number of steps each simulation runs for
+    #[arg(long, default_value_t = 300)]
+    pub steps: usize,
+    /// The number of the best parameter sets to save
+    #[arg(long, default_value_t = 5)]
+    pub keep: usize,
+    /// Where to save the presets
+    #[arg(long, default_value = "presets.toml")]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// The number of boids [default: the --boids before the subcommand]
+    #[arg(long)]
+    pub boids: Option<usize>,
+    /// The number of steps to run before saving the image
+    #[arg(long, default_value_t = 300)]
+    pub steps: usize,
+    /// The width of the image, in pixels
+    #[arg(long, default_value_t = 1920, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: u32,
+    /// The height of the image, in pixels
+    #[arg(long, default_value_t = 1080, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: u32,
+    /// Where to save the image
+    #[arg(long, default_value = "boids.png")]
+    pub output: PathBuf,
+    #[command(flatten)]
+    pub overlays: OverlayArgs,
+}
+
+#[derive(Args)]
+pub struct RecordArgs {
+    /// The number of boids [default: the --boids before the subcommand]
+    #[arg(long)]
+    pub boids: Option<usize>,
+    /// The number of steps to run before recording starts, so the flock has time to form
+    #[arg(long, default_value_t = 0)]
+    pub warmup: usize,
+    /// The number of frames to record - --record-frames is used instead, if it is given
+    #[arg(long, default_value_t = 300)]
+    pub frames: usize,
+    #[command(flatten)]
+    pub overlays: OverlayArgs,
+}
+
+#[derive(Args)]
+pub struct SvgArgs {
+    /// The number of boids [default: the --boids before the subcommand]
+    #[arg(long)]
+    pub boids: Option<usize>,
+    /// The number of steps to run before saving the SVG
+    #[arg(long, default_value_t = 300)]
+    pub steps: usize,
+    /// Where to save the SVG
+    #[arg(long, default_value = "boids.svg")]
+    pub output: PathBuf,
+    /// Add where every boid has recently been, as lines
+    #[arg(long)]
+    pub trails: bool,
+}
+
+#[derive(Args)]
+pub struct TuiArgs {
+    /// The characters the flock is drawn with
+    #[arg(long, value_enum, default_value_t = TuiMarker::Braille)]
+    pub marker: TuiMarker,
+}
+
+// What gets drawn on top of the boids in images made without a window
+#[derive(Args)]
+pub struct OverlayArgs {
+    /// Draw the trails of the boids
+    #[arg(long)]
+    pub trails: bool,
+    /// Draw the perception range of every boid
+    #[arg(long)]
+    pub perception: bool,
+    /// Draw the velocity and the forces of the three rules
+    #[arg(long)]
+    pub forces: bool,
+    /// Draw the density heatmap
+    #[arg(long)]
+    pub density: bool,
+    /// Draw the occupancy map, of where the boids have been
+    #[arg(long)]
+    pub occupancy: bool,
+}
+
+static CLI: OnceLock<Cli> = OnceLock::new();
+
+// The parsed arguments - nannou only hands the model function the app, so they are kept here
+pub fn cli() -> &'static Cli {
+    CLI.get_or_init(parse)
+}
+
+// Parses the arguments, and fills in the ones that weren't given from the config file - a broken
+// config file shouldn't stop the app, so just say what went wrong
+fn parse() -> Cli {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    cli.given = matches
+        .ids()
+        .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::CommandLine))
+        .map(ToString::to_string)
+        .collect();
+    let Some(path) = cli.config.clone() else {
+        return cli;
+    };
+    match load_config(&path) {
+        Ok((config, unknown)) => {
+            for key in unknown {
+                eprintln!("Ignoring the unknown setting {key} in {}", path.display());
+            }
+            let given = cli.given.clone();
+            config.fill_cli(&mut cli, |id| given.contains(id));
+            cli.file_config = config;
+        }
+        Err(error) => eprintln!("Unable to load the config from {}: {error}", path.display()),
+    }
+    cli
+}
@@ -0,0 +1,59 @@
+use crate::boid::Boid;
+use crate::spatial::SpatialGrid;
+
+use nannou::prelude::Rect;
+
+// Groups boids that are connected through chains of neighbours, and labels every boid with the
+// smallest id in its group
+pub fn label_clusters(flock: &mut [Boid], bounds: Rect) {
+    let reach = flock
+        .iter()
+        .map(Boid::perception_radius)
+        .fold(0.0, f32::max);
+    // Without any perception, every boid is a group of its own - the grid would only have the
+    // smallest cells, and nothing in them to find
+    if reach <= 0.0 {
+        for boid in flock {
+            boid.change_cluster(boid.id());
+        }
+        return;
+    }
+    let grid = SpatialGrid::new(bounds, reach, flock.iter().map(Boid::position));
+
+    // Union-find over the indices of the flock
+    let mut parents: Vec<usize> = (0..flock.len()).collect();
+    for (index, boid) in flock.iter().enumerate() {
+        for other_index in grid.candidates(boid.position(), boid.perception_radius()) {
+            // Only join every pair once
+            if other_index <= index {
+                continue;
+            }
+            let distance = boid.position().distance(flock[other_index].position());
+            if distance < boid.perception_radius() {
+                let root = find(&mut parents, index);
+                let other_root = find(&mut parents, other_index);
+                parents[root] = other_root;
+            }
+        }
+    }
+
+    // The smallest id of every group, stored at its root
+    let mut labels = vec![u64::MAX; flock.len()];
+    for (index, boid) in flock.iter().enumerate() {
+        let root = find(&mut parents, index);
+        labels[root] = labels[root].min(boid.id());
+    }
+    for (index, boid) in flock.iter_mut().enumerate() {
+        let root = find(&mut parents, index);
+        boid.change_cluster(labels[root]);
+    }
+}
+
+// Finds the root of a group, flattening the path to it on the way
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
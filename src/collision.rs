@@ -0,0 +1,159 @@
+use crate::boid::Boid;
+use crate::spatial::SpatialGrid;
+use crate::world::World;
+
+use nannou::prelude::{Rect, Vec2};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Pushes overlapping boids apart, so that no two discs overlap, without pushing them out of the
+// arena - returns the number of overlapping pairs that were found. The grid is kept between
+// steps, so its cells don't have to be allocated again
+pub fn resolve_collisions(flock: &mut [Boid], world: &World, grid: &mut SpatialGrid) -> usize {
+    let bounds = world.bounds;
+    // The largest possible distance between two touching boids
+    let reach = flock.iter().map(Boid::diameter).fold(0.0, f32::max);
+    if reach <= 0.0 {
+        return 0;
+    }
+    grid.rebuild(bounds, reach, flock.iter().map(Boid::position));
+    let grid = &*grid;
+    let wraps = world.wraps();
+    // Like in update, compare to a copy, so every boid sees the same positions
+    let temp_flock = flock.to_vec();
+    let collisions = AtomicUsize::new(0);
+
+    flock.par_iter_mut().enumerate().for_each(|(index, boid)| {
+        let mut correction = Vec2::ZERO;
+        let position = boid.position();
+        for image in images(position, reach, bounds, wraps) {
+            for other_index in grid.candidates(image, reach) {
+                if other_index == index {
+                    continue;
+                }
+                let other = &temp_flock[other_index];
+                let difference = image - other.position();
+                let distance = difference.length();
+                let overlap = boid.radius() + other.radius() - distance;
+                if overlap > 0.0 {
+                    // Only count every pair once
+                    if index < other_index {
+                        collisions.fetch_add(1, Ordering::Relaxed);
+                    }
+                    // Boids on top of each other get pushed apart in an arbitrary, but consistent,
+                    // direction
+                    let direction = if distance > 0.0 {
+                        difference / distance
+                    } else if index < other_index {
+                        Vec2::X
+                    } else {
+                        -Vec2::X
+                    };
+                    // Each boid moves its share of the overlap - the lighter one moves the most
+                    let share = other.mass() / (boid.mass() + other.mass());
+                    correction += direction * overlap * share;
+                }
+            }
+        }
+        boid.change_position(position + correction);
+        boid.keep_inside(world, position);
+    });
+
+    collisions.into_inner()
+}
+
+// The position, and where it would be on the other sides of a wrapping world if it is within reach
+// of the edges, so boids touching across the edges are found too
+fn images(position: Vec2, reach: f32, bounds: Rect, wraps: bool) -> impl Iterator<Item = Vec2> {
+    // How far to move along one axis to get to the other side, or nothing if it isn't near an edge
+    let shift = |value: f32, low: f32, high: f32, size: f32| {
+        if !wraps {
+            None
+        } else if value - reach < low {
+            Some(size)
+        } else if value + reach > high {
+            Some(-size)
+        } else {
+            None
+        }
+    };
+    // Boids wrap around a radius inside the edges, so the two sides are a diameter closer together
+    // than the size of the world
+    let x = shift(
+        position.x,
+        bounds.left(),
+        bounds.right(),
+        bounds.w() - reach,
+    );
+    let y = shift(
+        position.y,
+        bounds.bottom(),
+        bounds.top(),
+        bounds.h() - reach,
+    );
+    std::iter::once(0.0).chain(x).flat_map(move |x| {
+        std::iter::once(0.0)
+            .chain(y)
+            .map(move |y| position + Vec2::new(x, y))
+    })
+}
+
+// Counts the overlapping pairs of boids, without moving any of them
+pub fn count_overlaps(flock: &[Boid], bounds: Rect) -> usize {
+    let reach = flock.iter().map(Boid::diameter).fold(0.0, f32::max);
+    if reach <= 0.0 {
+        return 0;
+    }
+    let grid = SpatialGrid::new(bounds, reach, flock.iter().map(Boid::position));
+    flock
+        .par_iter()
+        .enumerate()
+        .map(|(index, boid)| {
+            grid.candidates(boid.position(), reach)
+                // Only count every pair once
+                .filter(|&other_index| other_index > index)
+                .filter(|&other_index| {
+                    let other = &flock[other_index];
+                    boid.position().distance(other.position()) < boid.radius() + other.radius()
+                })
+                .count()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Boundary;
+
+    fn pair(world: &World) -> [Boid; 2] {
+        [
+            Boid::new(Vec2::new(-93.0, 0.0), Vec2::X).with_diameter(10.0),
+            Boid::new(Vec2::new(93.0, 0.0), Vec2::X).with_diameter(10.0),
+        ]
+        .map(|mut boid| {
+            boid.keep_inside(world, boid.position());
+            boid
+        })
+    }
+
+    #[test]
+    fn boids_touching_across_a_wrapping_edge_are_pushed_apart() {
+        let world = World::new(Rect::from_w_h(200.0, 200.0));
+        let mut flock = pair(&world);
+        let count = resolve_collisions(&mut flock, &world, &mut SpatialGrid::default());
+        assert_eq!(count, 1);
+        // They are pushed away from each other, across the edge
+        assert!(flock[0].position().x > -93.0);
+        assert!(flock[1].position().x < 93.0);
+    }
+
+    #[test]
+    fn boids_dont_touch_across_walls() {
+        let mut world = World::new(Rect::from_w_h(200.0, 200.0));
+        world.boundary = Boundary::Bounce;
+        let mut flock = pair(&world);
+        let count = resolve_collisions(&mut flock, &world, &mut SpatialGrid::default());
+        assert_eq!(count, 0);
+    }
+}
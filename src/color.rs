@@ -1,5 +1,11 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+// The color behind everything
+pub const BACKGROUND: Color = Color::new(0.1569, 0.1569, 0.1569, 1.0);
+
 // So we can compare boids, which have a Color field, using ==
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -11,4 +17,199 @@ impl Color {
     pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
+
+    // Blends towards another color - 0.0 gives this color, and 1.0 gives the other one
+    pub fn lerp(self, other: Self, amount: f32) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let mix = |a: f32, b: f32| a + (b - a) * amount;
+        Self::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    // The color as bytes, for writing images
+    pub fn to_rgba8(self) -> [u8; 4] {
+        let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [byte(self.r), byte(self.g), byte(self.b), byte(self.a)]
+    }
+}
+
+// Builds a color from a hex code, like 0x21918c
+const fn hex(code: u32) -> Color {
+    Color::new(
+        ((code >> 16) & 0xff) as f32 / 255.0,
+        ((code >> 8) & 0xff) as f32 / 255.0,
+        (code & 0xff) as f32 / 255.0,
+        1.0,
+    )
+}
+
+// Builds a color from a hue between 0.0 and 1.0, at full saturation and value
+pub fn hue(hue: f32) -> Color {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    Color::new(r, g, b, 1.0)
+}
+
+// What the color of a boid shows
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorScheme {
+    // Speed as red, and the position as green and blue
+    #[default]
+    Classic,
+    // The direction the boid is facing, around the color wheel
+    HeadingHue,
+    Speed,
+    // The number of neighbours
+    Density,
+    Species,
+    // Which group of connected boids the boid is part of
+    Cluster,
+    // How aligned the neighbours of the boid are
+    LocalAlignment,
+    Flat,
+}
+
+impl ColorScheme {
+    const ALL: [Self; 8] = [
+        Self::Classic,
+        Self::HeadingHue,
+        Self::Speed,
+        Self::Density,
+        Self::Species,
+        Self::Cluster,
+        Self::LocalAlignment,
+        Self::Flat,
+    ];
+
+    // The next scheme, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&scheme| scheme == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::HeadingHue => "heading hue",
+            Self::Speed => "speed",
+            Self::Density => "neighbour density",
+            Self::Species => "species",
+            Self::Cluster => "cluster",
+            Self::LocalAlignment => "local alignment",
+            Self::Flat => "flat",
+        }
+    }
+}
+
+// The colors used by the schemes that aren't classic or heading hue
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    #[default]
+    Viridis,
+    Magma,
+    // Cividis for gradients, and Okabe and Ito's colors for categories
+    ColorblindSafe,
+}
+
+const VIRIDIS: [Color; 5] = [
+    hex(0x440154),
+    hex(0x3b528b),
+    hex(0x21918c),
+    hex(0x5ec962),
+    hex(0xfde725),
+];
+const MAGMA: [Color; 6] = [
+    hex(0x000004),
+    hex(0x3b0f70),
+    hex(0x8c2981),
+    hex(0xde4968),
+    hex(0xfe9f6d),
+    hex(0xfcfdbf),
+];
+const CIVIDIS: [Color; 6] = [
+    hex(0x00204d),
+    hex(0x31446b),
+    hex(0x666970),
+    hex(0x958f78),
+    hex(0xcbba69),
+    hex(0xffea46),
+];
+const OKABE_ITO: [Color; 7] = [
+    hex(0xe69f00),
+    hex(0x56b4e9),
+    hex(0x009e73),
+    hex(0xf0e442),
+    hex(0x0072b2),
+    hex(0xd55e00),
+    hex(0xcc79a7),
+];
+
+impl Palette {
+    const ALL: [Self; 3] = [Self::Viridis, Self::Magma, Self::ColorblindSafe];
+
+    // The next palette, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&palette| palette == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Viridis => "viridis",
+            Self::Magma => "magma",
+            Self::ColorblindSafe => "colorblind safe",
+        }
+    }
+
+    const fn stops(self) -> &'static [Color] {
+        match self {
+            Self::Viridis => &VIRIDIS,
+            Self::Magma => &MAGMA,
+            Self::ColorblindSafe => &CIVIDIS,
+        }
+    }
+
+    // A color along the gradient of the palette, from 0.0 to 1.0
+    pub fn gradient(self, t: f32) -> Color {
+        let stops = self.stops();
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        stops[index].lerp(stops[index + 1], position - index as f32)
+    }
+
+    // One of a set of distinct colors, for telling groups apart
+    pub fn category(self, index: usize) -> Color {
+        match self {
+            Self::ColorblindSafe => OKABE_ITO[index % OKABE_ITO.len()],
+            // Stepping by the golden ratio keeps neighbouring categories far apart on the gradient
+            _ => self.gradient((index as f32 * 0.618_034).fract()),
+        }
+    }
+}
+
+// How the boids are colored
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Coloring {
+    pub scheme: ColorScheme,
+    pub palette: Palette,
 }
@@ -0,0 +1,935 @@
+use crate::arena::Boundary;
+use crate::boid::{Boid, Parameters};
+use crate::cli::{cli, ArenaKind, Cli};
+use crate::color::{ColorScheme, Palette};
+use crate::falloff::Falloff;
+use crate::glyph::Glyph;
+use crate::keys::key_name;
+use crate::model::{load_keymap, Model};
+use crate::preset::Preset;
+use crate::steering::{Rule, SteeringMode};
+
+use rayon::prelude::*;
+use ron::extensions::Extensions;
+use ron::ser::PrettyConfig;
+use ron::Options;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// Everything a config file can set - whatever it leaves out stays as it was, and options given
+// on the command line win over it
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    // The name of the preset to start with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_preset: Option<String>,
+    pub world: WorldConfig,
+    pub flock: FlockConfig,
+    // The parameters every boid starts with
+    pub boid: BoidConfig,
+    pub colors: ColorConfig,
+    // Keys that do what another key does, like U = "L"
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+    // More presets to switch between, after the ones from --preset
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<Preset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub arena: Option<ArenaKind>,
+    pub boundary: Option<Boundary>,
+    pub steering: Option<SteeringMode>,
+    pub rules: Option<Vec<Rule>>,
+    pub species: Option<u8>,
+    pub collisions: Option<bool>,
+    pub food_sources: Option<usize>,
+    pub food_regrowth: Option<f32>,
+    pub alarm_delay: Option<u32>,
+    pub alarm_decay: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FlockConfig {
+    pub boids: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BoidConfig {
+    pub max_speed: Option<f32>,
+    pub max_force: Option<f32>,
+    pub perception_radius: Option<f32>,
+    pub alignment: Option<f32>,
+    pub cohesion: Option<f32>,
+    pub separation: Option<f32>,
+    pub alignment_falloff: Option<Falloff>,
+    pub cohesion_falloff: Option<Falloff>,
+    pub separation_falloff: Option<Falloff>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ColorConfig {
+    pub scheme: Option<ColorScheme>,
+    pub palette: Option<Palette>,
+    pub glyph: Option<Glyph>,
+}
+
+// How often the config file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Ron(ron::error::SpannedError),
+    SerializeToml(toml::ser::Error),
+    SerializeRon(ron::Error),
+    // A value that parsed, but makes no sense
+    Invalid(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Toml(error) => write!(f, "invalid config file: {error}"),
+            Self::Ron(error) => write!(f, "invalid config file: {error}"),
+            Self::SerializeToml(error) => write!(f, "unable to write the config: {error}"),
+            Self::SerializeRon(error) => write!(f, "unable to write the config: {error}"),
+            Self::Invalid(message) => write!(f, "invalid config file: {message}"),
+        }
+    }
+}
+
+impl Config {
+    // The live values of the model, as they are shown by the current values
+    pub fn from_model(model: &Model) -> Self {
+        let world = &model.world;
+        let parameters = model
+            .flock
+            .first()
+            .map_or(world.parameters, Boid::parameters);
+        Self {
+            start_preset: None,
+            world: WorldConfig {
+                width: Some(world.bounds.w()),
+                height: Some(world.bounds.h()),
+                arena: Some(world.arena.kind()),
+                boundary: Some(world.boundary),
+                steering: Some(world.steering),
+                rules: Some(world.rules.clone()),
+                species: Some(world.species),
+                collisions: Some(world.collisions),
+                food_sources: Some(world.foraging.sources),
+                food_regrowth: Some(world.foraging.regrowth),
+                alarm_delay: Some(world.alarms.delay),
+                alarm_decay: Some(world.alarms.decay),
+            },
+            flock: FlockConfig {
+                boids: Some(model.flock.len()),
+            },
+            boid: BoidConfig {
+                max_speed: Some(parameters.max_speed),
+                max_force: Some(parameters.max_force),
+                perception_radius: Some(parameters.perception_radius),
+                alignment: Some(parameters.alignment_mod),
+                cohesion: Some(parameters.cohesion_mod),
+                separation: Some(parameters.separation_mod),
+                alignment_falloff: Some(parameters.alignment_falloff),
+                cohesion_falloff: Some(parameters.cohesion_falloff),
+                separation_falloff: Some(parameters.separation_falloff),
+            },
+            colors: ColorConfig {
+                scheme: Some(world.coloring.scheme),
+                palette: Some(world.coloring.palette),
+                glyph: Some(model.glyphs.glyph),
+            },
+            keys: model
+                .keymap
+                .iter()
+                .map(|(&key, &action)| (key_name(key), key_name(action)))
+                .collect(),
+            presets: Vec::new(),
+        }
+    }
+
+    // Changes the running model to match the file, after it was reloaded over the previous one -
+    // the size of the world and the number of boids only take effect at startup, and the boids
+    // stay where they are
+    pub fn apply_live(&self, previous: &Config, model: &mut Model) {
+        let changes = self.changes(previous, &Cli::defaults(), |id| cli().given.contains(id));
+        let world = &mut model.world;
+        let settings = &changes.world;
+        if let Some(kind) = settings.arena {
+            if let Some(index) = model.arenas.iter().position(|arena| arena.kind() == kind) {
+                if index != model.arena_index {
+                    model.arena_index = index;
+                    world.change_arena(model.arenas[index].clone(), &mut model.flock);
+                }
+            }
+        }
+        set(&mut world.boundary, settings.boundary);
+        set(&mut world.steering, settings.steering);
+        set(&mut world.rules, settings.rules.clone());
+        set(&mut world.species, settings.species);
+        set(&mut world.collisions, settings.collisions);
+        set(&mut world.foraging.sources, settings.food_sources);
+        set(&mut world.foraging.regrowth, settings.food_regrowth);
+        set(&mut world.alarms.delay, settings.alarm_delay);
+        set(&mut world.alarms.decay, settings.alarm_decay);
+
+        world.parameters = changes.boid.apply(world.parameters);
+        model
+            .flock
+            .par_iter_mut()
+            .for_each(|boid| boid.set_parameters(&changes.boid.apply(boid.parameters())));
+
+        set(&mut world.coloring.scheme, changes.colors.scheme);
+        set(&mut world.coloring.palette, changes.colors.palette);
+        set(&mut model.glyphs.glyph, changes.colors.glyph);
+
+        // Keys can only be bound in the file
+        model.keymap = load_keymap(&self.keys);
+    }
+
+    // The settings that change when the file is reloaded over the previous one - the ones it sets,
+    // and the ones it stopped setting, which go back to their defaults. Like at startup, the
+    // options given on the command line win over the file, so they are left out
+    fn changes(&self, previous: &Config, defaults: &Cli, given: impl Fn(&str) -> bool) -> Config {
+        let (world, old) = (&self.world, &previous.world);
+        let (boid, old_boid) = (&self.boid, &previous.boid);
+        let (colors, old_colors) = (&self.colors, &previous.colors);
+        let parameters = defaults.parameters();
+        Config {
+            start_preset: None,
+            world: WorldConfig {
+                width: None,
+                height: None,
+                arena: changed(world.arena, old.arena, defaults.arena, given("arena")),
+                boundary: changed(
+                    world.boundary,
+                    old.boundary,
+                    defaults.boundary,
+                    given("boundary"),
+                ),
+                steering: changed(
+                    world.steering,
+                    old.steering,
+                    defaults.steering,
+                    given("steering"),
+                ),
+                rules: changed(
+                    world.rules.clone(),
+                    old.rules.clone(),
+                    defaults.rules.clone().unwrap_or_else(|| Rule::ALL.to_vec()),
+                    given("rules"),
+                ),
+                species: changed(
+                    world.species,
+                    old.species,
+                    defaults.species,
+                    given("species"),
+                ),
+                collisions: changed(
+                    world.collisions,
+                    old.collisions,
+                    defaults.collisions,
+                    given("collisions"),
+                ),
+                food_sources: changed(
+                    world.food_sources,
+                    old.food_sources,
+                    defaults.food_sources,
+                    given("food_sources"),
+                ),
+                food_regrowth: changed(
+                    world.food_regrowth,
+                    old.food_regrowth,
+                    defaults.food_regrowth,
+                    given("food_regrowth"),
+                ),
+                alarm_delay: changed(
+                    world.alarm_delay,
+                    old.alarm_delay,
+                    defaults.alarm_delay,
+                    given("alarm_delay"),
+                ),
+                alarm_decay: changed(
+                    world.alarm_decay,
+                    old.alarm_decay,
+                    defaults.alarm_decay,
+                    given("alarm_decay"),
+                ),
+            },
+            flock: FlockConfig::default(),
+            boid: BoidConfig {
+                max_speed: changed(
+                    boid.max_speed,
+                    old_boid.max_speed,
+                    parameters.max_speed,
+                    given("max_speed"),
+                ),
+                max_force: changed(
+                    boid.max_force,
+                    old_boid.max_force,
+                    parameters.max_force,
+                    given("max_force"),
+                ),
+                perception_radius: changed(
+                    boid.perception_radius,
+                    old_boid.perception_radius,
+                    parameters.perception_radius,
+                    given("perception_radius"),
+                ),
+                alignment: changed(
+                    boid.alignment,
+                    old_boid.alignment,
+                    parameters.alignment_mod,
+                    given("alignment"),
+                ),
+                cohesion: changed(
+                    boid.cohesion,
+                    old_boid.cohesion,
+                    parameters.cohesion_mod,
+                    given("cohesion"),
+                ),
+                separation: changed(
+                    boid.separation,
+                    old_boid.separation,
+                    parameters.separation_mod,
+                    given("separation"),
+                ),
+                alignment_falloff: changed(
+                    boid.alignment_falloff,
+                    old_boid.alignment_falloff,
+                    parameters.alignment_falloff,
+                    given("alignment_falloff"),
+                ),
+                cohesion_falloff: changed(
+                    boid.cohesion_falloff,
+                    old_boid.cohesion_falloff,
+                    parameters.cohesion_falloff,
+                    given("cohesion_falloff"),
+                ),
+                separation_falloff: changed(
+                    boid.separation_falloff,
+                    old_boid.separation_falloff,
+                    parameters.separation_falloff,
+                    given("separation_falloff"),
+                ),
+            },
+            colors: ColorConfig {
+                scheme: changed(
+                    colors.scheme,
+                    old_colors.scheme,
+                    defaults.color_scheme,
+                    given("color_scheme"),
+                ),
+                palette: changed(
+                    colors.palette,
+                    old_colors.palette,
+                    defaults.palette,
+                    given("palette"),
+                ),
+                glyph: changed(
+                    colors.glyph,
+                    old_colors.glyph,
+                    defaults.glyph,
+                    given("glyph"),
+                ),
+            },
+            keys: self.keys.clone(),
+            presets: Vec::new(),
+        }
+    }
+
+    // Fills in the arguments that weren't given on the command line
+    pub fn fill_cli(&self, cli: &mut Cli, given: impl Fn(&str) -> bool) {
+        let world = &self.world;
+        fill(&mut cli.world_width, world.width, given("world_width"));
+        fill(&mut cli.world_height, world.height, given("world_height"));
+        fill(&mut cli.arena, world.arena, given("arena"));
+        fill(&mut cli.boundary, world.boundary, given("boundary"));
+        fill(&mut cli.steering, world.steering, given("steering"));
+        fill(
+            &mut cli.rules,
+            world.rules.clone().map(Some),
+            given("rules"),
+        );
+        fill(&mut cli.species, world.species, given("species"));
+        fill(&mut cli.collisions, world.collisions, given("collisions"));
+        fill(
+            &mut cli.food_sources,
+            world.food_sources,
+            given("food_sources"),
+        );
+        fill(
+            &mut cli.food_regrowth,
+            world.food_regrowth,
+            given("food_regrowth"),
+        );
+        fill(
+            &mut cli.alarm_delay,
+            world.alarm_delay,
+            given("alarm_delay"),
+        );
+        fill(
+            &mut cli.alarm_decay,
+            world.alarm_decay,
+            given("alarm_decay"),
+        );
+
+        fill(&mut cli.boids, self.flock.boids, given("boids"));
+
+        let boid = &self.boid;
+        fill(
+            &mut cli.max_speed,
+            boid.max_speed.map(Some),
+            given("max_speed"),
+        );
+        fill(
+            &mut cli.max_force,
+            boid.max_force.map(Some),
+            given("max_force"),
+        );
+        fill(
+            &mut cli.perception_radius,
+            boid.perception_radius.map(Some),
+            given("perception_radius"),
+        );
+        fill(
+            &mut cli.alignment,
+            boid.alignment.map(Some),
+            given("alignment"),
+        );
+        fill(
+            &mut cli.cohesion,
+            boid.cohesion.map(Some),
+            given("cohesion"),
+        );
+        fill(
+            &mut cli.separation,
+            boid.separation.map(Some),
+            given("separation"),
+        );
+        fill(
+            &mut cli.alignment_falloff,
+            boid.alignment_falloff.map(Some),
+            given("alignment_falloff"),
+        );
+        fill(
+            &mut cli.cohesion_falloff,
+            boid.cohesion_falloff.map(Some),
+            given("cohesion_falloff"),
+        );
+        fill(
+            &mut cli.separation_falloff,
+            boid.separation_falloff.map(Some),
+            given("separation_falloff"),
+        );
+
+        fill(
+            &mut cli.color_scheme,
+            self.colors.scheme,
+            given("color_scheme"),
+        );
+        fill(&mut cli.palette, self.colors.palette, given("palette"));
+        fill(&mut cli.glyph, self.colors.glyph, given("glyph"));
+
+        fill(
+            &mut cli.start_preset,
+            self.start_preset.clone().map(Some),
+            given("start_preset"),
+        );
+    }
+
+    // Checks the values the command line would have refused, and the parameters of the presets
+    fn validate(&self) -> Result<(), String> {
+        let world = &self.world;
+        let boid = &self.boid;
+        check(
+            "world.width",
+            world.width,
+            |value| value > 0.0,
+            "greater than 0",
+        )?;
+        check(
+            "world.height",
+            world.height,
+            |value| value > 0.0,
+            "greater than 0",
+        )?;
+        check(
+            "world.food_regrowth",
+            world.food_regrowth,
+            |value| value >= 0.0,
+            "at least 0",
+        )?;
+        check(
+            "world.alarm_decay",
+            world.alarm_decay,
+            |value| (0.0..=1.0).contains(&value),
+            "from 0 to 1",
+        )?;
+        check(
+            "world.species",
+            world.species.map(f32::from),
+            |value| value >= 1.0,
+            "at least 1",
+        )?;
+        check_parameters(
+            |key| format!("boid.{key}"),
+            [
+                ("max_speed", boid.max_speed),
+                ("max_force", boid.max_force),
+                ("perception_radius", boid.perception_radius),
+            ],
+            [
+                ("alignment", boid.alignment),
+                ("cohesion", boid.cohesion),
+                ("separation", boid.separation),
+            ],
+        )?;
+        for preset in &self.presets {
+            check_preset(preset)?;
+        }
+        Ok(())
+    }
+}
+
+impl BoidConfig {
+    // The parameters, with the ones the file sets replaced
+    fn apply(&self, parameters: Parameters) -> Parameters {
+        Parameters {
+            max_speed: self.max_speed.unwrap_or(parameters.max_speed),
+            max_force: self.max_force.unwrap_or(parameters.max_force),
+            perception_radius: self
+                .perception_radius
+                .unwrap_or(parameters.perception_radius),
+            alignment_mod: self.alignment.unwrap_or(parameters.alignment_mod),
+            cohesion_mod: self.cohesion.unwrap_or(parameters.cohesion_mod),
+            separation_mod: self.separation.unwrap_or(parameters.separation_mod),
+            alignment_falloff: self
+                .alignment_falloff
+                .unwrap_or(parameters.alignment_falloff),
+            cohesion_falloff: self.cohesion_falloff.unwrap_or(parameters.cohesion_falloff),
+            separation_falloff: self
+                .separation_falloff
+                .unwrap_or(parameters.separation_falloff),
+        }
+    }
+}
+
+// Replaces a value with the one from the config file, if it has one
+fn set<T>(target: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *target = value;
+    }
+}
+
+// The value a setting changes to when the file is reloaded - the file's, or the default if the file
+// stopped setting it. Settings given on the command line, or left out of both files, don't change
+fn changed<T>(value: Option<T>, previous: Option<T>, default: T, given: bool) -> Option<T> {
+    match (value, previous) {
+        _ if given => None,
+        (Some(value), _) => Some(value),
+        (None, Some(_)) => Some(default),
+        (None, None) => None,
+    }
+}
+
+// Replaces a value with the one from the config file, unless it was given on the command line
+fn fill<T>(target: &mut T, value: Option<T>, given: bool) {
+    if let (Some(value), false) = (value, given) {
+        *target = value;
+    }
+}
+
+fn check(
+    name: &str,
+    value: Option<f32>,
+    valid: fn(f32) -> bool,
+    expected: &str,
+) -> Result<(), String> {
+    match value {
+        Some(value) if !value.is_finite() || !valid(value) => Err(format!(
+            "{name} should be a number {expected}, but is {value}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Checks the values of boid parameters by their keys - the positive ones are divided by, so
+// they can't be 0
+fn check_parameters(
+    name: impl Fn(&str) -> String,
+    positive: [(&str, Option<f32>); 3],
+    non_negative: [(&str, Option<f32>); 3],
+) -> Result<(), String> {
+    for (key, value) in positive {
+        check(&name(key), value, |value| value > 0.0, "greater than 0")?;
+    }
+    for (key, value) in non_negative {
+        check(&name(key), value, |value| value >= 0.0, "at least 0")?;
+    }
+    Ok(())
+}
+
+// Checks the parameters of a preset, from a config file or a preset file, with the same rules
+// as the [boid] table
+pub fn check_preset(preset: &Preset) -> Result<(), String> {
+    let parameters = &preset.parameters;
+    check_parameters(
+        |key| format!("{key} of the preset {}", preset.name),
+        [
+            ("max_speed", Some(parameters.max_speed)),
+            ("max_force", Some(parameters.max_force)),
+            ("perception_radius", Some(parameters.perception_radius)),
+        ],
+        [
+            ("alignment_mod", Some(parameters.alignment_mod)),
+            ("cohesion_mod", Some(parameters.cohesion_mod)),
+            ("separation_mod", Some(parameters.separation_mod)),
+        ],
+    )
+}
+
+// Config files are RON if they end in .ron, and TOML otherwise
+fn is_ron(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "ron")
+}
+
+// Options can be written without Some(...) in RON files
+fn ron_options() -> Options {
+    Options::default().with_default_extension(Extensions::IMPLICIT_SOME)
+}
+
+// Loads a config file, along with the keys in it that mean nothing, so they can be warned about
+pub fn load_config(path: &Path) -> Result<(Config, Vec<String>), ConfigError> {
+    let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+    let mut unknown = Vec::new();
+    let config: Config = if is_ron(path) {
+        let mut deserializer = ron::Deserializer::from_str_with_options(&text, ron_options())
+            .map_err(ConfigError::Ron)?;
+        let config = serde_ignored::deserialize(&mut deserializer, |key| {
+            unknown.push(key.to_string());
+        })
+        .map_err(|error| ConfigError::Ron(deserializer.span_error(error)))?;
+        deserializer
+            .end()
+            .map_err(|error| ConfigError::Ron(deserializer.span_error(error)))?;
+        config
+    } else {
+        let deserializer = toml::Deserializer::parse(&text).map_err(ConfigError::Toml)?;
+        serde_ignored::deserialize(deserializer, |key| unknown.push(key.to_string()))
+            .map_err(ConfigError::Toml)?
+    };
+    for (index, preset) in config.presets.iter().enumerate() {
+        unknown.extend(
+            preset
+                .unknown
+                .keys()
+                .map(|key| format!("presets.{index}.{key}")),
+        );
+    }
+    config.validate().map_err(ConfigError::Invalid)?;
+    Ok((config, unknown))
+}
+
+pub fn save_config(path: &Path, config: &Config) -> Result<(), ConfigError> {
+    let text = if is_ron(path) {
+        ron_options()
+            .to_string_pretty(config, PrettyConfig::default())
+            .map_err(ConfigError::SerializeRon)?
+    } else {
+        toml::to_string(config).map_err(ConfigError::SerializeToml)?
+    };
+    fs::write(path, text).map_err(ConfigError::Io)
+}
+
+// Notices when the config file changes while the app is running
+pub struct ConfigWatcher {
+    path: PathBuf,
+    // When the file was last changed, as of the last time it was loaded
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    // What went wrong with the last reload, or the settings it didn't know - shown over the flock
+    pub message: Option<String>,
+    // The config as it was last loaded, so the settings taken out of the file can be noticed
+    config: Config,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf, config: Config) -> Self {
+        let modified = modified(&path);
+        Self {
+            path,
+            modified,
+            last_check: Instant::now(),
+            message: None,
+            config,
+        }
+    }
+
+    // The config, and the one loaded before it, if the file changed since it was last loaded and
+    // could be loaded again
+    pub fn poll(&mut self) -> Option<(Config, Config)> {
+        if self.last_check.elapsed() < RELOAD_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let path = self.path.display();
+        match load_config(&self.path) {
+            Ok((config, unknown)) => {
+                self.message = (!unknown.is_empty()).then(|| {
+                    format!(
+                        "Ignoring the unknown settings in {path}: {}",
+                        unknown.join(", ")
+                    )
+                });
+                let previous = std::mem::replace(&mut self.config, config.clone());
+                Some((config, previous))
+            }
+            Err(error) => {
+                self.message = Some(format!("Unable to reload {path}: {error}"));
+                None
+            }
+        }
+    }
+}
+
+// When a file was last changed - editors can remove a file for a moment while saving it
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes a config file where no other test writes, and loads it back
+    fn load(name: &str, text: &str) -> Result<(Config, Vec<String>), ConfigError> {
+        let path = std::env::temp_dir().join(format!("boids-rs-{}-{name}", std::process::id()));
+        fs::write(&path, text).expect("Unable to write the test config");
+        let config = load_config(&path);
+        let _ = fs::remove_file(path);
+        config
+    }
+
+    fn invalid(name: &str, text: &str) -> String {
+        match load(name, text) {
+            Err(ConfigError::Invalid(message)) => message,
+            Err(error) => panic!("{name} didn't parse: {error}"),
+            Ok(_) => panic!("{name} should be invalid"),
+        }
+    }
+
+    fn preset(name: &str, parameters: Parameters) -> Preset {
+        Preset {
+            name: name.to_string(),
+            fitness: None,
+            color_scheme: None,
+            palette: None,
+            glyph: None,
+            parameters,
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn toml_files_are_loaded_with_their_unknown_keys() {
+        let (config, unknown) = load(
+            "config.toml",
+            "[world]\nwidth = 800.0\nspecies = 3\nwibble = 1\n\n[boid]\nmax_speed = 4.0\n\n[keys]\nU = \"L\"\n",
+        )
+        .unwrap_or_else(|error| panic!("{error}"));
+        assert_eq!(config.world.width, Some(800.0));
+        assert_eq!(config.world.height, None);
+        assert_eq!(config.world.species, Some(3));
+        assert_eq!(config.boid.max_speed, Some(4.0));
+        assert_eq!(config.keys.get("U").map(String::as_str), Some("L"));
+        assert_eq!(unknown, ["world.wibble"]);
+    }
+
+    #[test]
+    fn misspelled_preset_keys_are_reported() {
+        let (config, unknown) = load(
+            "presets.toml",
+            "[[presets]]\nname = \"fast\"\nmax_sped = 9.0\nmax_speed = 8.0\nmax_force = 0.1\nperception_radius = 50.0\nalignment_mod = 1.0\ncohesion_mod = 1.0\nseparation_mod = 1.0\nseperation_falloff = \"linear\"\ncolour_scheme = \"speed\"\n",
+        )
+        .unwrap_or_else(|error| panic!("{error}"));
+        assert_eq!(config.presets[0].parameters.max_speed, 8.0);
+        assert_eq!(
+            unknown,
+            [
+                "presets.0.colour_scheme",
+                "presets.0.max_sped",
+                "presets.0.seperation_falloff"
+            ]
+        );
+    }
+
+    #[test]
+    fn ron_files_can_leave_out_some() {
+        let (config, unknown) = load(
+            "config.ron",
+            "(world: (width: 640.0, collisions: true), flock: (boids: 50))",
+        )
+        .unwrap_or_else(|error| panic!("{error}"));
+        assert_eq!(config.world.width, Some(640.0));
+        assert_eq!(config.world.collisions, Some(true));
+        assert_eq!(config.flock.boids, Some(50));
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn broken_files_are_turned_down() {
+        assert!(matches!(
+            load("broken.toml", "[world\nwidth = 1"),
+            Err(ConfigError::Toml(_))
+        ));
+        assert!(matches!(
+            load("broken.ron", "(world: (width: \"wide\"))"),
+            Err(ConfigError::Ron(_))
+        ));
+    }
+
+    #[test]
+    fn values_the_command_line_would_refuse_are_invalid() {
+        assert_eq!(
+            invalid("width.toml", "[world]\nwidth = 0.0\n"),
+            "world.width should be a number greater than 0, but is 0"
+        );
+        assert_eq!(
+            invalid("decay.toml", "[world]\nalarm_decay = 1.5\n"),
+            "world.alarm_decay should be a number from 0 to 1, but is 1.5"
+        );
+        assert_eq!(
+            invalid("species.toml", "[world]\nspecies = 0\n"),
+            "world.species should be a number at least 1, but is 0"
+        );
+        assert_eq!(
+            invalid("separation.toml", "[boid]\nseparation = -1.0\n"),
+            "boid.separation should be a number at least 0, but is -1"
+        );
+        assert_eq!(
+            invalid("speed.toml", "[boid]\nmax_speed = nan\n"),
+            "boid.max_speed should be a number greater than 0, but is NaN"
+        );
+    }
+
+    #[test]
+    fn presets_are_checked() {
+        assert!(check_preset(&preset("fine", Parameters::default())).is_ok());
+        let broken = preset(
+            "broken",
+            Parameters {
+                perception_radius: 0.0,
+                ..Parameters::default()
+            },
+        );
+        assert_eq!(
+            check_preset(&broken),
+            Err("perception_radius of the preset broken should be a number greater than 0, but is 0".to_string())
+        );
+        let config = Config {
+            presets: vec![broken],
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn reloading_changes_what_the_file_sets_and_resets_what_it_stopped_setting() {
+        let previous = Config {
+            world: WorldConfig {
+                boundary: Some(Boundary::Bounce),
+                species: Some(3),
+                ..WorldConfig::default()
+            },
+            boid: BoidConfig {
+                cohesion: Some(2.0),
+                ..BoidConfig::default()
+            },
+            ..Config::default()
+        };
+        let config = Config {
+            world: WorldConfig {
+                steering: Some(SteeringMode::Prioritised),
+                alarm_delay: Some(9),
+                ..WorldConfig::default()
+            },
+            ..Config::default()
+        };
+        let defaults = Cli::defaults();
+        let changes = config.changes(&previous, &defaults, |id| id == "alarm_delay");
+        // Set by the file
+        assert!(changes.world.steering == Some(SteeringMode::Prioritised));
+        // Taken out of the file
+        assert!(changes.world.boundary == Some(Boundary::Wrap));
+        assert_eq!(changes.world.species, Some(1));
+        assert_eq!(
+            changes.boid.cohesion,
+            Some(Parameters::default().cohesion_mod)
+        );
+        // Given on the command line
+        assert_eq!(changes.world.alarm_delay, None);
+        // In neither file
+        assert_eq!(changes.world.food_sources, None);
+        assert_eq!(changes.boid.max_speed, None);
+    }
+
+    #[test]
+    fn saved_configs_load_the_same() {
+        let config = Config {
+            start_preset: Some("murmuration".to_string()),
+            world: WorldConfig {
+                width: Some(1000.0),
+                alarm_decay: Some(0.5),
+                ..WorldConfig::default()
+            },
+            boid: BoidConfig {
+                cohesion: Some(0.25),
+                separation_falloff: Some(Falloff::Gaussian),
+                ..BoidConfig::default()
+            },
+            ..Config::default()
+        };
+        for extension in ["toml", "ron"] {
+            let path = std::env::temp_dir()
+                .join(format!("boids-rs-{}-saved.{extension}", std::process::id()));
+            if let Err(error) = save_config(&path, &config) {
+                panic!("Unable to save the {extension} config: {error}");
+            }
+            let loaded = load_config(&path);
+            let _ = fs::remove_file(&path);
+            let (loaded, unknown) = loaded.unwrap_or_else(|error| panic!("{error}"));
+            assert!(unknown.is_empty());
+            assert_eq!(loaded.start_preset.as_deref(), Some("murmuration"));
+            assert_eq!(loaded.world.width, Some(1000.0));
+            assert_eq!(loaded.world.alarm_decay, Some(0.5));
+            assert_eq!(loaded.boid.cohesion, Some(0.25));
+            assert!(loaded.boid.separation_falloff == Some(Falloff::Gaussian));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::boid::Boid;
+use crate::color::{Color, Palette};
+use crate::export::{write_csv, write_heatmap, ExportError};
+
+use nannou::prelude::{Draw, Rect, Vec2};
+use std::path::Path;
+
+// The size of a cell of the grid, in pixels
+const CELL_SIZE: f32 = 20.0;
+// The number of boids in a cell which gets the last color of the palette
+const SATURATION: f32 = 8.0;
+// How opaque the fullest cells are drawn
+const MAX_ALPHA: f32 = 0.6;
+
+// Counts the boids in every cell of a grid over the world - both right now, and added up over
+// time, like a long exposure of where the flock has been
+pub struct DensityMap {
+    pub show_density: bool,
+    pub show_occupancy: bool,
+    bounds: Rect,
+    cols: usize,
+    rows: usize,
+    // The number of boids in each cell in the last step
+    density: Vec<f32>,
+    // The number of boids in each cell, added up over every step since it was cleared
+    occupancy: Vec<f32>,
+    // The number of steps added up in occupancy
+    steps: u64,
+}
+
+impl DensityMap {
+    pub fn new(bounds: Rect) -> Self {
+        let cols = (bounds.w() / CELL_SIZE).ceil().max(1.0) as usize;
+        let rows = (bounds.h() / CELL_SIZE).ceil().max(1.0) as usize;
+        Self {
+            show_density: false,
+            show_occupancy: false,
+            bounds,
+            cols,
+            rows,
+            density: vec![0.0; cols * rows],
+            occupancy: vec![0.0; cols * rows],
+            steps: 0,
+        }
+    }
+
+    // Starts over with an empty grid covering the new bounds, if they changed
+    pub fn fit(&mut self, bounds: Rect) {
+        if self.bounds != bounds {
+            *self = Self {
+                show_density: self.show_density,
+                show_occupancy: self.show_occupancy,
+                ..Self::new(bounds)
+            };
+        }
+    }
+
+    // The number of boids in each cell in the last step, and added up over the steps since the
+    // occupancy was cleared, along with the number of those steps
+    pub fn counts(&self) -> (&[f32], &[f32], u64) {
+        (&self.density, &self.occupancy, self.steps)
+    }
+
+    // Puts back saved counts - they only fit if the grid is the same size
+    pub fn set_counts(&mut self, density: Vec<f32>, occupancy: Vec<f32>, steps: u64) {
+        if density.len() == self.density.len() && occupancy.len() == self.occupancy.len() {
+            self.density = density;
+            self.occupancy = occupancy;
+            self.steps = steps;
+        }
+    }
+
+    // Forgets where the flock has been
+    pub fn clear_occupancy(&mut self) {
+        self.occupancy.fill(0.0);
+        self.steps = 0;
+    }
+
+    // The index of the cell a position is in - positions outside go in the edge cells
+    fn cell_of(&self, position: Vec2) -> usize {
+        let cell = (position - self.bounds.bottom_left()) / CELL_SIZE;
+        let col = (cell.x.max(0.0) as usize).min(self.cols - 1);
+        let row = (cell.y.max(0.0) as usize).min(self.rows - 1);
+        row * self.cols + col
+    }
+
+    // Counts where the boids are now, and adds it to where they have been
+    pub fn step(&mut self, flock: &[Boid]) {
+        self.density.fill(0.0);
+        for boid in flock {
+            let cell = self.cell_of(boid.position());
+            self.density[cell] += 1.0;
+        }
+        for (occupancy, density) in self.occupancy.iter_mut().zip(&self.density) {
+            *occupancy += density;
+        }
+        self.steps += 1;
+    }
+
+    // The average number of boids in each cell since the occupancy was cleared
+    fn mean_occupancy(&self) -> Vec<f32> {
+        let steps = self.steps.max(1) as f32;
+        self.occupancy.iter().map(|value| value / steps).collect()
+    }
+
+    // The most boids a cell has had on average, so the busiest cell gets the last color
+    fn occupancy_saturation(&self) -> f32 {
+        let largest = self.occupancy.iter().copied().fold(0.0, f32::max);
+        largest / self.steps.max(1) as f32
+    }
+
+    pub fn show_density(&self, draw: &Draw, palette: Palette) {
+        show_cells(draw, &self.density_cells(palette));
+    }
+
+    pub fn show_occupancy(&self, draw: &Draw, palette: Palette) {
+        show_cells(draw, &self.occupancy_cells(palette));
+    }
+
+    // The cells of the density heatmap, and their colors - empty cells are skipped
+    pub fn density_cells(&self, palette: Palette) -> Vec<(Rect, Color)> {
+        self.cells(&self.density, SATURATION, palette)
+    }
+
+    // The cells of the occupancy map, and their colors - empty cells are skipped
+    pub fn occupancy_cells(&self, palette: Palette) -> Vec<(Rect, Color)> {
+        self.cells(&self.mean_occupancy(), self.occupancy_saturation(), palette)
+    }
+
+    // Colors a grid of values as a translucent heatmap
+    fn cells(&self, values: &[f32], saturation: f32, palette: Palette) -> Vec<(Rect, Color)> {
+        if saturation <= 0.0 {
+            return Vec::new();
+        }
+        values
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value > 0.0)
+            .map(|(index, &value)| {
+                let amount = (value / saturation).min(1.0);
+                let color = palette.gradient(amount);
+                let cell = Vec2::new((index % self.cols) as f32, (index / self.cols) as f32);
+                let centre = self.bounds.bottom_left() + (cell + 0.5) * CELL_SIZE;
+                (
+                    Rect::from_xy_wh(centre, Vec2::splat(CELL_SIZE)),
+                    Color::new(color.r, color.g, color.b, amount * MAX_ALPHA),
+                )
+            })
+            .collect()
+    }
+
+    // Writes the density and the occupancy to a directory, as both images and CSV files, named
+    // after the number of steps the occupancy covers
+    pub fn export(&self, directory: &Path, palette: Palette) -> Result<(), ExportError> {
+        let scale = CELL_SIZE as u32;
+        let occupancy = self.mean_occupancy();
+        let name = |kind: &str, extension: &str| {
+            directory.join(format!("{kind}-{:06}.{extension}", self.steps))
+        };
+        write_heatmap(
+            &name("density", "png"),
+            self.cols,
+            &self.density,
+            SATURATION,
+            palette,
+            scale,
+        )?;
+        write_csv(&name("density", "csv"), self.cols, &self.density)?;
+        write_heatmap(
+            &name("occupancy", "png"),
+            self.cols,
+            &occupancy,
+            self.occupancy_saturation(),
+            palette,
+            scale,
+        )?;
+        write_csv(&name("occupancy", "csv"), self.cols, &occupancy)?;
+        Ok(())
+    }
+}
+
+fn show_cells(draw: &Draw, cells: &[(Rect, Color)]) {
+    for (rect, color) in cells {
+        draw.rect()
+            .xy(rect.xy())
+            .wh(rect.wh())
+            .rgba(color.r, color.g, color.b, color.a);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::boid::Boid;
+use crate::random::random_range;
+
+use nannou::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// How many population sizes are kept for the plot in the current values
+pub const POPULATION_HISTORY: usize = 512;
+
+// The optional population dynamics - boids spend energy, eat, die, and reproduce
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Ecology {
+    pub enabled: bool,
+    // The energy spent each step, just for being alive
+    pub idle_cost: f32,
+    // The energy spent each step, per unit of speed and force
+    pub speed_cost: f32,
+    pub force_cost: f32,
+    // Boids with more energy than this split off a child
+    pub birth_threshold: f32,
+    // The fraction by which inherited values can change, in either direction
+    pub mutation: f32,
+    // No more children are born once the flock is this big
+    pub max_population: usize,
+    pub population_history: VecDeque<usize>,
+}
+
+impl Ecology {
+    // Moves the ecology one step forward - killing and giving birth to boids
+    pub fn step(&mut self, flock: &mut Vec<Boid>) {
+        if !self.enabled {
+            return;
+        }
+
+        // Every boid pays for its movement - this is cheap, so no need for rayon
+        for boid in flock.iter_mut() {
+            boid.metabolise(self.idle_cost, self.speed_cost, self.force_cost);
+        }
+
+        // Deaths, then births - the flock is only changed after all the boids have been updated
+        flock.retain(Boid::is_alive);
+        let population = flock.len();
+        let mut children = Vec::new();
+        for parent in flock.iter_mut() {
+            if population + children.len() >= self.max_population {
+                break;
+            }
+            if parent.energy() > self.birth_threshold {
+                children.push(parent.reproduce(self.mutation));
+            }
+        }
+        flock.extend(children);
+
+        self.population_history.push_back(flock.len());
+        if self.population_history.len() > POPULATION_HISTORY {
+            self.population_history.pop_front();
+        }
+    }
+}
+
+impl Default for Ecology {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_cost: 0.01,
+            speed_cost: 0.01,
+            force_cost: 1.0,
+            birth_threshold: 200.0,
+            mutation: 0.1,
+            max_population: 4096,
+            population_history: VecDeque::with_capacity(POPULATION_HISTORY),
+        }
+    }
+}
+
+// Multiplies a value by a random factor between 1 - mutation and 1 + mutation
+pub fn mutate(value: f32, mutation: f32) -> f32 {
+    value * (1.0 + random_range(-mutation, mutation))
+}
+
+// A random offset, so children don't spawn exactly on top of their parents
+pub fn random_offset(distance: f32) -> Vec2 {
+    Vec2::new(
+        random_range(-distance, distance),
+        random_range(-distance, distance),
+    )
+}
@@ -0,0 +1,98 @@
+use crate::color::Palette;
+use crate::snapshot::SnapshotError;
+
+use nannou::image::{self, ImageError, RgbaImage};
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub enum ExportError {
+    Io(io::Error),
+    Image(ImageError),
+    // Animated PNGs are written with the png crate, since image can't write them
+    Png(png::EncodingError),
+    // Images can't be empty, or too large to fit in memory
+    Size(u32, u32),
+    Snapshot(SnapshotError),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Image(error) => write!(f, "unable to write the image: {error}"),
+            Self::Png(error) => write!(f, "unable to write the animation: {error}"),
+            Self::Size(width, height) => {
+                write!(f, "unable to make an image of {width}x{height} pixels")
+            }
+            Self::Snapshot(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ImageError> for ExportError {
+    fn from(error: ImageError) -> Self {
+        Self::Image(error)
+    }
+}
+
+impl From<png::EncodingError> for ExportError {
+    fn from(error: png::EncodingError) -> Self {
+        Self::Png(error)
+    }
+}
+
+impl From<SnapshotError> for ExportError {
+    fn from(error: SnapshotError) -> Self {
+        Self::Snapshot(error)
+    }
+}
+
+// Writes a grid of values as a CSV file, one line per row, with the top row first
+pub fn write_csv(path: &Path, cols: usize, values: &[f32]) -> Result<(), ExportError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    for row in values.chunks(cols).rev() {
+        let line: Vec<String> = row.iter().map(f32::to_string).collect();
+        writeln!(file, "{}", line.join(","))?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Writes a grid of values as an image, colored along the gradient of a palette - every cell
+// becomes a square of scale by scale pixels, and values of saturation or more get the last color
+pub fn write_heatmap(
+    path: &Path,
+    cols: usize,
+    values: &[f32],
+    saturation: f32,
+    palette: Palette,
+    scale: u32,
+) -> Result<(), ExportError> {
+    let rows = values.len() / cols.max(1);
+    let image = RgbaImage::from_fn(cols as u32 * scale, rows as u32 * scale, |x, y| {
+        // The image starts at the top, and the grid at the bottom
+        let col = (x / scale) as usize;
+        let row = rows - 1 - (y / scale) as usize;
+        let value = values[row * cols + col] / saturation.max(f32::EPSILON);
+        image::Rgba(palette.gradient(value).to_rgba8())
+    });
+    image.save(path)?;
+    Ok(())
+}
+
+// The first file in a directory named like stem-000.extension that doesn't exist yet, so
+// exporting again doesn't overwrite the last export
+pub fn numbered_path(directory: &Path, stem: &str, extension: &str) -> PathBuf {
+    (0..)
+        .map(|number| directory.join(format!("{stem}-{number:03}.{extension}")))
+        .find(|path| !path.exists())
+        .expect("There is a free number")
+}
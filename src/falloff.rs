@@ -0,0 +1,122 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+// The width of the gaussian, as a fraction of the perception radius
+const GAUSSIAN_WIDTH: f32 = 0.4;
+// Distances are at least this, so the inverse curves don't blow up
+const MIN_DISTANCE: f32 = 1.0;
+
+// How much a neighbour counts, depending on how far away it is
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Falloff {
+    // Every neighbour counts the same
+    #[default]
+    Constant,
+    // Falls off in a straight line, to zero at the edge of perception
+    Linear,
+    Inverse,
+    InverseSquare,
+    // A bell curve, which is almost zero at the edge of perception
+    Gaussian,
+    // An s-curve, which is exactly zero at the edge of perception
+    Smoothstep,
+}
+
+impl Falloff {
+    const ALL: [Self; 6] = [
+        Self::Constant,
+        Self::Linear,
+        Self::Inverse,
+        Self::InverseSquare,
+        Self::Gaussian,
+        Self::Smoothstep,
+    ];
+
+    // The weight of a neighbour at a distance, for a boid which can see up to the radius
+    pub fn weight(self, distance: f32, radius: f32) -> f32 {
+        // How far towards the edge of perception the neighbour is
+        let t = (distance / radius).clamp(0.0, 1.0);
+        let distance = distance.max(MIN_DISTANCE);
+        match self {
+            Self::Constant => 1.0,
+            Self::Linear => 1.0 - t,
+            Self::Inverse => 1.0 / distance,
+            Self::InverseSquare => 1.0 / (distance * distance),
+            Self::Gaussian => (-(t * t) / (2.0 * GAUSSIAN_WIDTH * GAUSSIAN_WIDTH)).exp(),
+            Self::Smoothstep => 1.0 - t * t * (3.0 - 2.0 * t),
+        }
+    }
+
+    // The next curve, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&falloff| falloff == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    // The previous curve, wrapping around
+    pub fn previous(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&falloff| falloff == self)
+            .unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Constant => "constant",
+            Self::Linear => "linear",
+            Self::Inverse => "inverse",
+            Self::InverseSquare => "inverse square",
+            Self::Gaussian => "gaussian",
+            Self::Smoothstep => "smoothstep",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_fall_off_towards_the_edge_of_perception() {
+        for falloff in Falloff::ALL {
+            let near = falloff.weight(10.0, 100.0);
+            let far = falloff.weight(90.0, 100.0);
+            assert!(near >= far, "{} gets heavier further away", falloff.name());
+            assert!(far >= 0.0, "{} is negative", falloff.name());
+        }
+    }
+
+    #[test]
+    fn curves_have_the_right_values_at_the_ends() {
+        assert_eq!(Falloff::Constant.weight(100.0, 100.0), 1.0);
+        assert_eq!(Falloff::Linear.weight(0.0, 100.0), 1.0);
+        assert_eq!(Falloff::Linear.weight(100.0, 100.0), 0.0);
+        assert_eq!(Falloff::Smoothstep.weight(0.0, 100.0), 1.0);
+        assert_eq!(Falloff::Smoothstep.weight(100.0, 100.0), 0.0);
+        assert_eq!(Falloff::Gaussian.weight(0.0, 100.0), 1.0);
+        assert!(Falloff::Gaussian.weight(100.0, 100.0) < 0.05);
+        assert_eq!(Falloff::Inverse.weight(4.0, 100.0), 0.25);
+        assert_eq!(Falloff::InverseSquare.weight(4.0, 100.0), 0.0625);
+    }
+
+    #[test]
+    fn inverse_curves_dont_blow_up_up_close() {
+        assert_eq!(Falloff::Inverse.weight(0.0, 100.0), 1.0);
+        assert_eq!(Falloff::InverseSquare.weight(0.0, 100.0), 1.0);
+    }
+
+    #[test]
+    fn next_and_previous_wrap_around() {
+        assert!(Falloff::Smoothstep.next() == Falloff::Constant);
+        assert!(Falloff::Constant.previous() == Falloff::Smoothstep);
+        for falloff in Falloff::ALL {
+            assert!(falloff.next().previous() == falloff);
+        }
+    }
+}
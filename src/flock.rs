@@ -1,23 +1,25 @@
 use crate::boid::Boid;
+use crate::random::random_range;
+use crate::world::World;
 
-use nannou::prelude::{random_range, Rect, Vec2};
+use nannou::prelude::Vec2;
 
 pub struct Flock;
 
 impl Flock {
-    pub fn new_flock(boundary_rect: Rect, flock_size: usize) -> Vec<Boid> {
+    pub fn new_flock(world: &World, flock_size: usize) -> Vec<Boid> {
         let mut flock: Vec<Boid> = Vec::new();
-        for _ in 0..flock_size {
-            flock.push(Boid::new(
-                // Position vector
-                Vec2::new(
-                    // Random, inside the rect
-                    random_range(boundary_rect.left(), boundary_rect.right()),
-                    random_range(boundary_rect.bottom(), boundary_rect.top()),
-                ),
+        for i in 0..flock_size {
+            let mut boid = Boid::new(
+                // Position vector - random, but inside the arena
+                world.random_position(),
                 // Velocity vector - random, but clamped to max_speed
                 Vec2::new(random_range(-1.0, 1.0), random_range(-1.0, 1.0)).clamp_length_max(0.075),
-            ));
+            );
+            boid.set_parameters(&world.parameters);
+            // Split the flock evenly between the species
+            boid.change_species((i % world.species.max(1) as usize) as u8);
+            flock.push(boid);
         }
         flock
     }
@@ -0,0 +1,115 @@
+use crate::arena::Arena;
+use crate::boid::Boid;
+use crate::color::Color;
+
+use nannou::prelude::{Draw, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+// A patch of food, which boids can eat from when they are inside it
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Food {
+    pub position: Vec2,
+    pub amount: f32,
+    pub max_amount: f32,
+    pub radius: f32,
+}
+
+impl Food {
+    pub const fn new(position: Vec2, max_amount: f32, radius: f32) -> Self {
+        Self {
+            position,
+            amount: max_amount,
+            max_amount,
+            radius,
+        }
+    }
+
+    // Takes at most `bite` food from the patch, returning how much was actually taken
+    pub fn eat(&mut self, bite: f32) -> f32 {
+        let eaten = bite.min(self.amount);
+        self.amount -= eaten;
+        eaten
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amount <= 0.0
+    }
+
+    // Draws the patch as a green circle, which fades as it gets eaten
+    pub fn show(&self, draw: &Draw) {
+        let color = self.color();
+        draw.ellipse()
+            .xy(self.position)
+            .radius(self.radius)
+            .rgba(color.r, color.g, color.b, color.a);
+    }
+
+    pub fn color(&self) -> Color {
+        Color::new(0.4, 0.8, 0.3, 0.1 + 0.3 * self.amount / self.max_amount)
+    }
+}
+
+// All the food in the world, and how it behaves
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Foraging {
+    pub food: Vec<Food>,
+    // The number of patches generated at random
+    pub sources: usize,
+    pub amount: f32,
+    pub radius: f32,
+    // How much food grows back in every patch each step
+    pub regrowth: f32,
+    // How much food a boid can take from a patch each step
+    pub bite: f32,
+    // The total amount of food eaten, for comparing how well flocks forage
+    pub eaten: f32,
+}
+
+impl Foraging {
+    // Replaces all the food with new patches at random positions inside the arena
+    pub fn randomise(&mut self, arena: &Arena, bounds: Rect) {
+        self.food = (0..self.sources)
+            .map(|_| Food::new(arena.random_point(bounds), self.amount, self.radius))
+            .collect();
+    }
+
+    // Adds a full patch at a position
+    pub fn place(&mut self, position: Vec2) {
+        self.food
+            .push(Food::new(position, self.amount, self.radius));
+    }
+
+    // Lets the boids eat, and the food grow back - boids only gain energy from it when `feed` is set
+    pub fn step(&mut self, flock: &mut [Boid], feed: bool) {
+        // Feeding is sequential, so two boids can't both eat the last of a patch
+        for food in &mut self.food {
+            for boid in flock.iter_mut() {
+                if food.is_empty() {
+                    break;
+                }
+                if boid.position().distance(food.position) < food.radius {
+                    let eaten = food.eat(self.bite);
+                    self.eaten += eaten;
+                    if feed {
+                        boid.feed(eaten);
+                    }
+                }
+            }
+            food.amount = (food.amount + self.regrowth).min(food.max_amount);
+        }
+    }
+}
+
+impl Default for Foraging {
+    fn default() -> Self {
+        Self {
+            food: Vec::new(),
+            sources: 12,
+            amount: 400.0,
+            radius: 40.0,
+            regrowth: 0.5,
+            bite: 1.0,
+            eaten: 0.0,
+        }
+    }
+}
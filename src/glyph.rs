@@ -0,0 +1,182 @@
+use clap::ValueEnum;
+use nannou::prelude::{Vec2, TAU};
+use nannou::wgpu::Texture;
+use serde::{Deserialize, Serialize};
+
+// The number of corners of the circle glyph
+const CIRCLE_RESOLUTION: usize = 16;
+
+// What a boid is drawn as
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Glyph {
+    // The original isoceles triangle
+    #[default]
+    Triangle,
+    // A triangle with a notch cut out of the back
+    Dart,
+    // A circle, with a tick pointing where the boid is heading
+    Circle,
+    Fish,
+    Bird,
+    // An arrow that gets longer the faster the boid flies
+    Arrow,
+    // The image given with --sprite, rotated to where the boid is heading
+    Sprite,
+}
+
+// A part of a glyph, in the coordinates of the boid, where it is heading to the right
+pub enum Shape {
+    Fill(Vec<Vec2>),
+    Stroke(Vec<Vec2>),
+}
+
+impl Shape {
+    // The same shape, with every point moved by a function
+    pub fn map(self, f: impl Fn(Vec2) -> Vec2) -> Self {
+        match self {
+            Self::Fill(points) => Self::Fill(points.into_iter().map(f).collect()),
+            Self::Stroke(points) => Self::Stroke(points.into_iter().map(f).collect()),
+        }
+    }
+}
+
+impl Glyph {
+    pub const ALL: [Self; 7] = [
+        Self::Triangle,
+        Self::Dart,
+        Self::Circle,
+        Self::Fish,
+        Self::Bird,
+        Self::Arrow,
+        Self::Sprite,
+    ];
+
+    // The next glyph, wrapping around - the sprite is skipped if there is no image for it
+    pub fn next(self, has_sprite: bool) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|&glyph| glyph == self)
+            .unwrap_or(0);
+        let next = Self::ALL[(index + 1) % Self::ALL.len()];
+        if next == Self::Sprite && !has_sprite {
+            next.next(has_sprite)
+        } else {
+            next
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Triangle => "triangle",
+            Self::Dart => "dart",
+            Self::Circle => "circle",
+            Self::Fish => "fish",
+            Self::Bird => "bird",
+            Self::Arrow => "speed arrow",
+            Self::Sprite => "sprite",
+        }
+    }
+
+    // The outline of the glyph, for a boid of the given radius, flying at the given fraction
+    // of its max speed - the sprite has no outline, so it is a triangle wherever an outline is
+    // needed
+    pub fn shapes(self, radius: f32, speed: f32) -> Vec<Shape> {
+        let r = radius;
+        let points = |points: &[(f32, f32)]| {
+            points
+                .iter()
+                .map(|&(x, y)| Vec2::new(x * r, y * r))
+                .collect()
+        };
+        match self {
+            // A triangle pointing to the right - so it has an angle of zero degrees
+            // Basically looks like this, where the `o` is the origin:
+            //
+            //     *
+            //      o *  --->  |>
+            //     *
+            //
+            Self::Triangle | Self::Sprite => {
+                vec![Shape::Fill(points(&[
+                    (1.0, 0.0),
+                    (-1.0, -1.0),
+                    (-1.0, 1.0),
+                ]))]
+            }
+            Self::Dart => vec![Shape::Fill(points(&[
+                (1.0, 0.0),
+                (-1.0, -1.0),
+                (-0.5, 0.0),
+                (-1.0, 1.0),
+            ]))],
+            Self::Circle => vec![
+                Shape::Fill(ellipse(Vec2::ZERO, Vec2::splat(0.7 * r))),
+                Shape::Stroke(points(&[(0.0, 0.0), (1.4, 0.0)])),
+            ],
+            Self::Fish => vec![
+                // The body, with the head at the front
+                Shape::Fill(ellipse(
+                    Vec2::new(0.2 * r, 0.0),
+                    Vec2::new(0.8 * r, 0.4 * r),
+                )),
+                // The tail fin
+                Shape::Fill(points(&[(-0.4, 0.0), (-1.0, -0.5), (-1.0, 0.5)])),
+            ],
+            Self::Bird => vec![Shape::Fill(points(&[
+                (1.0, 0.0),
+                (0.2, 0.2),
+                (-0.2, 1.0),
+                (-0.4, 0.2),
+                (-1.0, 0.3),
+                (-0.8, 0.0),
+                (-1.0, -0.3),
+                (-0.4, -0.2),
+                (-0.2, -1.0),
+                (0.2, -0.2),
+            ]))],
+            Self::Arrow => {
+                // Twice as long as the boid at full speed, and half as long when standing still
+                let tip = 3.0 * speed.clamp(0.0, 1.0);
+                vec![
+                    Shape::Stroke(points(&[(-1.0, 0.0), (tip - 0.6, 0.0)])),
+                    Shape::Fill(points(&[(tip, 0.0), (tip - 0.6, -0.4), (tip - 0.6, 0.4)])),
+                ]
+            }
+        }
+    }
+}
+
+// The corners of an ellipse, going around counterclockwise
+fn ellipse(center: Vec2, radii: Vec2) -> Vec<Vec2> {
+    (0..CIRCLE_RESOLUTION)
+        .map(|i| {
+            let angle = i as f32 / CIRCLE_RESOLUTION as f32 * TAU;
+            center + Vec2::new(angle.cos(), angle.sin()) * radii
+        })
+        .collect()
+}
+
+// How the boids are drawn
+#[derive(Default)]
+pub struct Glyphs {
+    // The glyph every boid is drawn with
+    pub glyph: Glyph,
+    // The glyph of each species, used instead when per_species is on - species past the end of
+    // the list wrap around to the start
+    pub species: Vec<Glyph>,
+    pub per_species: bool,
+    // The image the sprite glyph is drawn with, if one was loaded
+    pub sprite: Option<Texture>,
+}
+
+impl Glyphs {
+    // The glyph a boid of a species is drawn with
+    pub fn of(&self, species: u8) -> Glyph {
+        if self.per_species && !self.species.is_empty() {
+            self.species[species as usize % self.species.len()]
+        } else {
+            self.glyph
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::boid::Boid;
+use crate::camera::Camera;
+use crate::cli::{cli, output_format, OutputFormat, OverlayArgs, RecordArgs, RenderArgs, SvgArgs};
+use crate::export::ExportError;
+use crate::flock::Flock;
+use crate::glyph::Glyphs;
+use crate::keys::Keybinds;
+use crate::metrics::{mean_nearest_neighbour_distance, polarisation};
+use crate::model::{cli_arena_index, load_arenas, load_cli_presets, load_glyphs, new_world};
+use crate::raster::render;
+use crate::record::Recorder;
+use crate::scene::Scene;
+use crate::snapshot::{load_cli_snapshot, save_snapshot, Snapshot};
+use crate::svg::write_svg;
+use crate::trail::Trails;
+use crate::world::World;
+
+use nannou::prelude::Rect;
+
+// A simulation without a window, set up by the command line like the model is
+pub struct Headless {
+    pub world: World,
+    pub flock: Vec<Boid>,
+    pub trails: Trails,
+    pub glyphs: Glyphs,
+    pub keybinds: Keybinds,
+}
+
+impl Headless {
+    pub fn new(boids: usize) -> Self {
+        let world_rect = Rect::from_w_h(cli().world_width, cli().world_height);
+        let arenas = load_arenas();
+        let mut world = new_world(world_rect, arenas[cli_arena_index(&arenas)].clone());
+        let mut flock = Flock::new_flock(&world, boids);
+        // Sprites live on the GPU, so they are drawn as triangles here
+        let mut glyphs = load_glyphs(None);
+        let (presets, index) = load_cli_presets();
+        if let Some(preset) = index.and_then(|index| presets.get(index)) {
+            preset.apply(&mut flock, &mut world, &mut glyphs);
+        }
+        let mut trails = Trails::default();
+        if let Some(snapshot) = load_cli_snapshot() {
+            let arena = snapshot.arena();
+            snapshot.restore(&mut world, &mut flock, &mut trails, &mut glyphs, &arenas);
+            if world.arena.kind() != arena {
+                eprintln!("The snapshot's arena isn't loaded, so the rectangle is used instead");
+            }
+        }
+        Self {
+            world,
+            flock,
+            trails,
+            glyphs,
+            keybinds: Keybinds::default(),
+        }
+    }
+
+    // Draws what the command line asked for on top of the boids
+    pub fn show_overlays(&mut self, overlays: &OverlayArgs) {
+        self.trails.enabled = overlays.trails;
+        self.keybinds.highlight_all = overlays.perception;
+        self.keybinds.show_forces = overlays.forces;
+        self.world.density.show_density = overlays.density;
+        self.world.density.show_occupancy = overlays.occupancy;
+    }
+
+    pub fn step(&mut self) {
+        self.world.step(&mut self.flock);
+        self.trails.push(&self.flock);
+    }
+
+    pub fn scene(&self) -> Scene<'_> {
+        Scene {
+            world: &self.world,
+            flock: &self.flock,
+            trails: &self.trails,
+            glyphs: &self.glyphs,
+            keybinds: &self.keybinds,
+            selected: None,
+        }
+    }
+}
+
+// Runs the simulation without a window, and saves how it looks at the end
+pub fn run_render(args: &RenderArgs) -> Result<(), ExportError> {
+    let mut headless = Headless::new(args.boids.unwrap_or(cli().boids));
+    headless.show_overlays(&args.overlays);
+
+    for _ in 0..args.steps {
+        headless.step();
+    }
+    render(&headless.scene(), args.width, args.height)?.save(&args.output)?;
+    println!("Saved the flock to {}", args.output.display());
+    Ok(())
+}
+
+// Runs the simulation without a window, recording a fixed number of frames
+pub fn run_record(args: &RecordArgs) -> Result<(), ExportError> {
+    let mut headless = Headless::new(args.boids.unwrap_or(cli().boids));
+    headless.show_overlays(&args.overlays);
+    for _ in 0..args.warmup {
+        headless.step();
+    }
+
+    let mut recorder = Recorder::from_cli();
+    let frames = cli().record_frames.unwrap_or(args.frames);
+    recorder.limit_frames(frames);
+    recorder.start()?;
+    let camera = Camera::fitting(headless.world.bounds, recorder.frame_rect());
+    for _ in 0..frames * recorder.every() {
+        headless.step();
+        if recorder.capture(&headless.scene(), &camera)? {
+            break;
+        }
+    }
+    recorder.stop();
+    if let Some(saved) = recorder.wait() {
+        println!("{}", saved?);
+    }
+    Ok(())
+}
+
+// Runs the simulation without a window, and saves the flock as vector shapes at the end
+pub fn run_svg(args: &SvgArgs) -> Result<(), ExportError> {
+    let mut headless = Headless::new(args.boids.unwrap_or(cli().boids));
+    headless.trails.record = args.trails;
+    for _ in 0..args.steps {
+        headless.step();
+    }
+    let trails = args.trails.then_some(cli().svg_tolerance);
+    write_svg(&args.output, &headless.scene(), trails)?;
+    println!("Saved the flock to {}", args.output.display());
+    Ok(())
+}
+
+// Runs the simulation without a window for as long as the command line says, and says how the
+// flock ended up - saving it too, and a snapshot to carry on from, if there is somewhere to save them
+pub fn run_headless() -> Result<(), ExportError> {
+    let mut headless = Headless::new(cli().boids);
+    for _ in 0..cli().steps {
+        headless.step();
+    }
+
+    println!("Steps: {}", cli().steps);
+    if let Some(seed) = cli().seed {
+        println!("Seed: {seed}");
+    }
+    println!("Boids: {}", headless.flock.len());
+    println!("Polarisation: {:.3}", polarisation(&headless.flock));
+    println!(
+        "Mean nearest neighbour distance: {:.1}",
+        mean_nearest_neighbour_distance(&headless.flock)
+    );
+
+    if let Some(path) = &cli().save_snapshot {
+        let snapshot = Snapshot::capture(
+            &headless.world,
+            &headless.flock,
+            &headless.trails,
+            &headless.glyphs,
+        );
+        save_snapshot(path, &snapshot)?;
+        println!("Saved the snapshot to {}", path.display());
+    }
+
+    let Some(output) = &cli().output else {
+        return Ok(());
+    };
+    match output_format(output) {
+        Some(OutputFormat::Svg) => write_svg(output, &headless.scene(), None)?,
+        // The path was checked when it was parsed, so anything else is an image
+        _ => render(&headless.scene(), cli().window_width, cli().window_height)?.save(output)?,
+    }
+    println!("Saved the flock to {}", output.display());
+    Ok(())
+}
@@ -1,97 +1,531 @@
 use crate::boid::Boid;
+use crate::cli::cli;
+use crate::config::{save_config, Config};
+use crate::export::numbered_path;
+use crate::falloff::Falloff;
 use crate::flock::Flock;
 use crate::model::Model;
+use crate::preset::{save_presets, Preset};
+use crate::random::random_range;
+use crate::snapshot::{load_snapshot, save_snapshot, slot_path, SNAPSHOT_SLOTS};
+use crate::svg::write_svg;
+use crate::text::HELP_PAGES;
+use crate::trail::TRAIL_CAPACITY;
 
-use nannou::prelude::{random_range, App, Key, Vec2};
+use nannou::prelude::{App, Key, Vec2};
 use rayon::prelude::*;
 
+// The keys A to Z, in order, so letters can be looked up by their position in the alphabet
+pub const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+pub const DIGITS: [Key; 10] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+];
+
+// The other keys that do something, so they can be named in config files
+const OTHER_KEYS: [Key; 19] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::Plus,
+    Key::Minus,
+    Key::LBracket,
+    Key::RBracket,
+    Key::Comma,
+    Key::Period,
+    Key::Semicolon,
+    Key::Apostrophe,
+    Key::Up,
+    Key::Down,
+];
+
 pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
-    match key {
+    let population = model.flock.len();
+    press(model, key, app.keys.mods.shift());
+    // Set the new window title, if boids were added or removed
+    if model.flock.len() != population {
+        app.main_window()
+            .set_title(format!("{} boids!", model.flock.len()).as_str());
+    }
+}
+
+pub fn key_released(_app: &App, model: &mut Model, key: Key) {
+    release(model, key);
+}
+
+// What a key does, whether it comes from the window or the terminal - keys that toggle something
+// return early while a key is held, so holding them down only toggles once
+pub fn press(model: &mut Model, key: Key, shift: bool) {
+    // Keys bound in the config file do what the key they are bound to does
+    match model.keymap.get(&key).copied().unwrap_or(key) {
         Key::Plus => {
             // Add a new boid
             // Copy the first boid and add it, if there is a first boid
             // TODO: Refactor this to not use .expect() and be more graceful
             if model.flock.is_empty() {
-                model.flock.push(Boid::default());
+                let mut boid = Boid::default();
+                boid.set_parameters(&model.world.parameters);
+                model.flock.push(boid);
             } else {
                 let mut new_boid = model
                     .flock
                     .first()
-                    .copied()
+                    .map(Boid::duplicate)
                     .expect("We checked that there is a first boid");
                 new_boid.change_position(Vec2::ZERO);
                 new_boid
                     .change_velocity(Vec2::new(random_range(-0.1, 0.1), random_range(-0.1, 0.1)));
                 model.flock.push(new_boid);
             }
-            // Set the new window title
-            app.main_window()
-                .set_title(format!("{} boids!", model.flock.len()).as_str());
+        }
+        Key::N => {
+            // Add a boid of a random size, with a mass to match
+            let mut boid = Boid::new(
+                Vec2::ZERO,
+                Vec2::new(random_range(-0.1, 0.1), random_range(-0.1, 0.1)),
+            )
+            .with_diameter(random_range(5.0, 20.0));
+            boid.set_parameters(&model.world.parameters);
+            model.flock.push(boid);
         }
         Key::Minus => {
             model.flock.pop();
-            // Set the new window title
-            app.main_window()
-                .set_title(format!("{} boids!", model.flock.len()).as_str());
         }
         Key::R => {
             // Reset the boids //
-            if !model.keybinds.any_is_pressed {
-                model.flock = Flock::new_flock(app.window_rect(), model.flock.len());
-                model.keybinds.any_is_pressed = true;
+            if model.keybinds.any_is_pressed {
+                return;
             }
+            model.flock = Flock::new_flock(&model.world, model.flock.len());
+            model.keybinds.any_is_pressed = true;
         }
         Key::T => {
-            // Soft reset
-            if !model.keybinds.any_is_pressed {
-                model.flock.par_iter_mut().for_each(|boid| {
-                    boid.change_position(Vec2::new(
-                        random_range(model.win_rect.left(), model.win_rect.right()),
-                        random_range(model.win_rect.bottom(), model.win_rect.top()),
-                    ));
-                    boid.change_position(Vec2::new(
-                        random_range(model.win_rect.left(), model.win_rect.right()),
-                        random_range(model.win_rect.bottom(), model.win_rect.top()),
-                    ));
+            // Soft reset - one boid at a time, so the random positions come in the same order
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            for boid in &mut model.flock {
+                boid.change_position(model.world.random_position());
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::K => {
+            // Toggle pushing overlapping boids apart
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.world.collisions = !model.world.collisions;
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::E => {
+            // Toggle energy, food, births and deaths
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.world.ecology.enabled = !model.world.ecology.enabled;
+            model.world.ecology.population_history.clear();
+            // The boids would starve without any food
+            if model.world.ecology.enabled && model.world.foraging.food.is_empty() {
+                model
+                    .world
+                    .foraging
+                    .randomise(&model.world.arena, model.world.bounds);
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F => {
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if shift {
+                // Remove all the food
+                model.world.foraging.food.clear();
+            } else {
+                // Scatter new food at random
+                model
+                    .world
+                    .foraging
+                    .randomise(&model.world.arena, model.world.bounds);
+            }
+            model.world.foraging.eaten = 0.0;
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::Y => {
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if shift {
+                // Steer the other way along the pheromone gradient
+                model
+                    .flock
+                    .par_iter_mut()
+                    .for_each(|boid| boid.flip_pheromone_modifier());
+            } else {
+                // Toggle the pheromone trails
+                model.world.pheromones.enabled = !model.world.pheromones.enabled;
+                model.world.pheromones.clear();
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::A => {
+            // Startle a random boid, making it dart forwards
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if !model.flock.is_empty() {
+                let index = random_range(0, model.flock.len());
+                let boid = &mut model.flock[index];
+                let heading = boid.velocity();
+                model.world.startle(boid, heading);
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::P => {
+            // Switch between adding up the forces and prioritising them
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.world.steering = model.world.steering.toggled();
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::M => {
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            let coloring = &mut model.world.coloring;
+            if shift {
+                // Switch to the next palette
+                coloring.palette = coloring.palette.next();
+            } else {
+                // Switch to the next color scheme
+                coloring.scheme = coloring.scheme.next();
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::G => {
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            let glyphs = &mut model.glyphs;
+            if shift {
+                // Switch between one glyph for all boids, and one for each species
+                glyphs.per_species = !glyphs.per_species;
+            } else {
+                // Switch to the next glyph
+                glyphs.glyph = glyphs.glyph.next(glyphs.sprite.is_some());
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F1 => {
+            // Toggle the density heatmap
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.world.density.show_density = !model.world.density.show_density;
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F2 => {
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if shift {
+                // Start the occupancy map over
+                model.world.density.clear_occupancy();
+            } else {
+                // Toggle the occupancy map
+                model.world.density.show_occupancy = !model.world.density.show_occupancy;
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F3 => {
+            // Save the density and the occupancy as images and CSV files
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            let density = &model.world.density;
+            match density.export(&cli().export_dir, model.world.coloring.palette) {
+                Ok(()) => model.say(format!(
+                    "Saved the heatmaps to {}",
+                    cli().export_dir.display()
+                )),
+                Err(error) => model.say(format!("Unable to export the heatmaps: {error}")),
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F4 => {
+            // Start or stop recording
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if model.recorder.recording {
+                model.recorder.stop();
+            } else if let Err(error) = model.recorder.start() {
+                model.say(format!("Unable to start recording: {error}"));
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F5 => {
+            // Save the flock as an SVG - with where the boids have been, if shift is held
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            let path = numbered_path(&cli().export_dir, "boids", "svg");
+            let trails = shift.then_some(cli().svg_tolerance);
+            match write_svg(&path, &model.scene(), trails) {
+                Ok(()) => model.say(format!("Saved the flock to {}", path.display())),
+                Err(error) => model.say(format!("Unable to save the SVG: {error}")),
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F6 => {
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if shift {
+                // Save the current values as a config file, to start with them later
+                let path = numbered_path(&cli().export_dir, "config", "toml");
+                match save_config(&path, &Config::from_model(model)) {
+                    Ok(()) => model.say(format!("Saved the config to {}", path.display())),
+                    Err(error) => model.say(format!("Unable to save the config: {error}")),
+                }
+            } else {
+                // Save the current values as a preset, and switch to it
+                let path = numbered_path(&cli().export_dir, "preset", "toml");
+                let name = path
+                    .file_stem()
+                    .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+                let parameters = model
+                    .flock
+                    .first()
+                    .map_or(model.world.parameters, Boid::parameters);
+                let preset = Preset::live(name, parameters, &model.world, &model.glyphs);
+                match save_presets(&path, std::slice::from_ref(&preset)) {
+                    Ok(()) => {
+                        model.say(format!("Saved the preset to {}", path.display()));
+                        model.presets.push(preset);
+                        model.preset_index = Some(model.presets.len() - 1);
+                    }
+                    Err(error) => model.say(format!("Unable to save the preset: {error}")),
+                }
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F7 => {
+            // Quick-save everything to the current slot
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            let path = slot_path(model.snapshot_slot);
+            match save_snapshot(&path, &model.snapshot()) {
+                Ok(()) => model.say(format!("Saved the snapshot to {}", path.display())),
+                Err(error) => model.say(format!("Unable to save the snapshot: {error}")),
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F8 => {
+            // Quick-load everything from the current slot
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            let path = slot_path(model.snapshot_slot);
+            match load_snapshot(&path) {
+                Ok(snapshot) => {
+                    model.restore_snapshot(snapshot);
+                    model.say(format!("Loaded the snapshot from {}", path.display()));
+                }
+                Err(error) => model.say(format!(
+                    "Unable to load the snapshot from {}: {error}",
+                    path.display()
+                )),
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::F9 => {
+            // Switch to the next quick-save slot, or the previous one if shift is held
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.snapshot_slot = if shift {
+                (model.snapshot_slot + SNAPSHOT_SLOTS - 1) % SNAPSHOT_SLOTS
+            } else {
+                (model.snapshot_slot + 1) % SNAPSHOT_SLOTS
+            };
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::B => {
+            // Switch to the next arena
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if !model.arenas.is_empty() {
+                model.arena_index = (model.arena_index + 1) % model.arenas.len();
+                let arena = model.arenas[model.arena_index].clone();
+                model.world.change_arena(arena, &mut model.flock);
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::O => {
+            // Fit the whole world in the window
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.camera.fit(model.world.bounds, model.win_rect);
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::I => {
+            // Toggle the camera following the selected boid
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.keybinds.follow_selected = !model.keybinds.follow_selected;
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::Q => {
+            // Toggle drawing the steering forces
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.keybinds.show_forces = !model.keybinds.show_forces;
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::W => {
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            if shift {
+                // Toggle not clearing the background
+                model.trails.accumulate = !model.trails.accumulate;
+            } else {
+                // Toggle the trails, starting them afresh
+                model.trails.enabled = !model.trails.enabled;
+                model.trails.clear();
+            }
+            model.keybinds.any_is_pressed = true;
+        }
+        // Trail length
+        Key::Comma => {
+            model.trails.length = model.trails.length.saturating_sub(1).max(2);
+        }
+        Key::Period => {
+            model.trails.length = (model.trails.length + 1).min(TRAIL_CAPACITY);
+        }
+        // Trail alpha
+        Key::Semicolon => {
+            model.trails.alpha = (model.trails.alpha - 0.01).max(0.0);
+        }
+        Key::Apostrophe => {
+            model.trails.alpha = (model.trails.alpha + 0.01).min(1.0);
+        }
+        Key::L => {
+            // Switch to the next preset, or the previous one if shift is held
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            let count = model.presets.len();
+            if count > 0 {
+                model.preset_index = Some(match (model.preset_index, shift) {
+                    (Some(index), false) => (index + 1) % count,
+                    (Some(index), true) => (index + count - 1) % count,
+                    (None, false) => 0,
+                    (None, true) => count - 1,
                 });
-                model.keybinds.any_is_pressed = true;
+                model.apply_preset();
             }
+            model.keybinds.any_is_pressed = true;
         }
         Key::S => {
             model.keybinds.highlight_all = true;
         }
         Key::D => {
-            if !model.keybinds.any_is_pressed {
-                model.keybinds.highlight_all = !model.keybinds.highlight_all;
-                model.keybinds.any_is_pressed = true;
+            if model.keybinds.any_is_pressed {
+                return;
             }
+            model.keybinds.highlight_all = !model.keybinds.highlight_all;
+            model.keybinds.any_is_pressed = true;
         }
         Key::Z => {
             model.keybinds.highlight_first = true;
         }
         Key::X => {
-            if !model.keybinds.any_is_pressed {
-                model.keybinds.highlight_first = !model.keybinds.highlight_first;
-                model.keybinds.any_is_pressed = true;
+            if model.keybinds.any_is_pressed {
+                return;
             }
+            model.keybinds.highlight_first = !model.keybinds.highlight_first;
+            model.keybinds.any_is_pressed = true;
         }
         Key::H => {
             model.keybinds.show_help_menu = true;
         }
         Key::J => {
-            if !model.keybinds.any_is_pressed {
-                model.keybinds.show_help_menu = !model.keybinds.show_help_menu;
-                model.keybinds.any_is_pressed = true;
+            if model.keybinds.any_is_pressed {
+                return;
+            }
+            model.keybinds.show_help_menu = !model.keybinds.show_help_menu;
+            model.keybinds.any_is_pressed = true;
+        }
+        Key::U => {
+            if model.keybinds.any_is_pressed {
+                return;
             }
+            // Turn the pages of the help menu, round to the first or the last
+            let pages = HELP_PAGES.len();
+            model.keybinds.help_page = if shift {
+                (model.keybinds.help_page + pages - 1) % pages
+            } else {
+                (model.keybinds.help_page + 1) % pages
+            };
+            model.keybinds.any_is_pressed = true;
         }
         Key::C => {
             model.keybinds.show_current_values = true;
         }
         Key::V => {
-            if !model.keybinds.any_is_pressed {
-                model.keybinds.show_current_values = !model.keybinds.show_current_values;
-                model.keybinds.any_is_pressed = true;
+            if model.keybinds.any_is_pressed {
+                return;
             }
+            model.keybinds.show_current_values = !model.keybinds.show_current_values;
+            model.keybinds.any_is_pressed = true;
         }
         // The keys for modifying the boids //
         // Perception range
@@ -139,6 +573,43 @@ pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
                 boid.change_max_force(1.01);
             });
         }
+        // Falloffs of the three rules, while holding shift
+        Key::Key5 if shift => cycle_falloff(
+            model,
+            Boid::alignment_falloff,
+            Boid::change_alignment_falloff,
+            Falloff::previous,
+        ),
+        Key::Key6 if shift => cycle_falloff(
+            model,
+            Boid::alignment_falloff,
+            Boid::change_alignment_falloff,
+            Falloff::next,
+        ),
+        Key::Key7 if shift => cycle_falloff(
+            model,
+            Boid::cohesion_falloff,
+            Boid::change_cohesion_falloff,
+            Falloff::previous,
+        ),
+        Key::Key8 if shift => cycle_falloff(
+            model,
+            Boid::cohesion_falloff,
+            Boid::change_cohesion_falloff,
+            Falloff::next,
+        ),
+        Key::Key9 if shift => cycle_falloff(
+            model,
+            Boid::separation_falloff,
+            Boid::change_separation_falloff,
+            Falloff::previous,
+        ),
+        Key::Key0 if shift => cycle_falloff(
+            model,
+            Boid::separation_falloff,
+            Boid::change_separation_falloff,
+            Falloff::next,
+        ),
         // Alignment modifier
         Key::Key5 => {
             model.flock.par_iter_mut().for_each(|boid| {
@@ -176,8 +647,29 @@ pub fn key_pressed(app: &App, model: &mut Model, key: Key) {
     }
 }
 
-pub fn key_released(_app: &App, model: &mut Model, key: Key) {
-    match key {
+// Gives every boid the falloff before or after the one the first boid has
+fn cycle_falloff(
+    model: &mut Model,
+    get: fn(&Boid) -> Falloff,
+    set: fn(&mut Boid, Falloff),
+    step: fn(Falloff) -> Falloff,
+) {
+    if model.keybinds.any_is_pressed {
+        return;
+    }
+    if let Some(first) = model.flock.first() {
+        let falloff = step(get(first));
+        model
+            .flock
+            .par_iter_mut()
+            .for_each(|boid| set(boid, falloff));
+    }
+    model.keybinds.any_is_pressed = true;
+}
+
+// Keys that only do something while they are held stop doing it
+pub fn release(model: &mut Model, key: Key) {
+    match model.keymap.get(&key).copied().unwrap_or(key) {
         Key::S => {
             model.keybinds.highlight_all = false;
         }
@@ -201,7 +693,11 @@ pub struct Keybinds {
     pub highlight_all: bool,
     pub highlight_first: bool,
     pub show_help_menu: bool,
+    // Which page of the help menu is shown
+    pub help_page: usize,
     pub show_current_values: bool,
+    pub follow_selected: bool,
+    pub show_forces: bool,
     // The is_pressed is for preventing the behavior that holding down a key repeatedly creates
     pub any_is_pressed: bool,
 }
@@ -212,8 +708,62 @@ impl Default for Keybinds {
             highlight_all: false,
             highlight_first: false,
             show_help_menu: true,
+            help_page: 0,
             show_current_values: false,
+            follow_selected: false,
+            show_forces: false,
             any_is_pressed: false,
         }
     }
 }
+
+// The name of a key in config files
+pub fn key_name(key: Key) -> String {
+    format!("{key:?}")
+}
+
+// The key with a name, like "A", "F1", "LBracket" or "Key1" - digits can also be given on
+// their own
+pub fn key_from_name(name: &str) -> Option<Key> {
+    if let Some(digit) = name
+        .parse::<usize>()
+        .ok()
+        .filter(|&digit| digit < DIGITS.len())
+    {
+        return Some(DIGITS[digit]);
+    }
+    LETTERS
+        .into_iter()
+        .chain(DIGITS)
+        .chain(OTHER_KEYS)
+        .find(|&key| key_name(key).eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_found_by_their_names() {
+        assert_eq!(key_from_name("A"), Some(Key::A));
+        assert_eq!(key_from_name("a"), Some(Key::A));
+        assert_eq!(key_from_name("F1"), Some(Key::F1));
+        assert_eq!(key_from_name("LBracket"), Some(Key::LBracket));
+        assert_eq!(key_from_name("Key7"), Some(Key::Key7));
+        assert_eq!(key_from_name("7"), Some(Key::Key7));
+    }
+
+    #[test]
+    fn unknown_names_are_no_key() {
+        assert_eq!(key_from_name(""), None);
+        assert_eq!(key_from_name("10"), None);
+        assert_eq!(key_from_name("Wibble"), None);
+    }
+
+    #[test]
+    fn names_lead_back_to_their_keys() {
+        for key in LETTERS.into_iter().chain(DIGITS).chain(OTHER_KEYS) {
+            assert_eq!(key_from_name(&key_name(key)), Some(key));
+        }
+    }
+}
@@ -1,17 +1,99 @@
+use cli::{cli, Command};
 use model::Model;
 use update::{update, view};
 
+mod alarm;
+mod arena;
 mod boid;
+mod camera;
+mod cli;
+mod cluster;
+mod collision;
 mod color;
+mod config;
+mod density;
+mod ecology;
+mod export;
+mod falloff;
 mod flock;
+mod food;
+mod glyph;
+mod headless;
 mod keys;
 mod math;
+mod metrics;
 mod model;
+mod optimise;
+mod pheromone;
+mod preset;
+mod random;
+mod raster;
+mod record;
+mod scene;
+mod snapshot;
+mod spatial;
+mod steering;
+mod svg;
 mod text;
+mod trail;
+mod tui;
 mod update;
 mod window;
+mod world;
 
 fn main() {
+    // The same seed gives the same run
+    if let Some(seed) = cli().seed {
+        random::seed(seed);
+    }
+
+    // Everything but the simulation in the window runs without one
+    match &cli().command {
+        Some(Command::Optimise(args)) => {
+            if let Err(error) = optimise::run(args) {
+                eprintln!("Unable to optimise: {error}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Render(args)) => {
+            if let Err(error) = headless::run_render(args) {
+                eprintln!("Unable to render: {error}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Record(args)) => {
+            if let Err(error) = headless::run_record(args) {
+                eprintln!("Unable to record: {error}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Svg(args)) => {
+            if let Err(error) = headless::run_svg(args) {
+                eprintln!("Unable to save the SVG: {error}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Tui(args)) => {
+            if let Err(error) = tui::run(args) {
+                eprintln!("Unable to run in the terminal: {error}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        None if cli().headless => {
+            if let Err(error) = headless::run_headless() {
+                eprintln!("Unable to save the flock: {error}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
     // Setting up the app
     nannou::app(Model::new).update(update).run();
 }
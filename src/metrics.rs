@@ -0,0 +1,37 @@
+use crate::boid::Boid;
+
+use nannou::prelude::Vec2;
+use rayon::prelude::*;
+
+// How aligned the flock is - 1.0 when every boid faces the same way, and close to 0.0 when
+// they face random directions
+pub fn polarisation(flock: &[Boid]) -> f32 {
+    if flock.is_empty() {
+        return 0.0;
+    }
+    let heading_sum = flock
+        .iter()
+        .map(|boid| boid.velocity().normalize_or_zero())
+        .fold(Vec2::ZERO, |sum, heading| sum + heading);
+    heading_sum.length() / flock.len() as f32
+}
+
+// The average distance from each boid to the boid closest to it
+pub fn mean_nearest_neighbour_distance(flock: &[Boid]) -> f32 {
+    if flock.len() < 2 {
+        return 0.0;
+    }
+    let total: f32 = flock
+        .par_iter()
+        .enumerate()
+        .map(|(index, boid)| {
+            flock
+                .iter()
+                .enumerate()
+                .filter(|&(other_index, _)| other_index != index)
+                .map(|(_, other)| boid.position().distance(other.position()))
+                .fold(f32::INFINITY, f32::min)
+        })
+        .sum();
+    total / flock.len() as f32
+}
@@ -1,16 +1,57 @@
+use crate::arena::{Arena, Mask};
 use crate::boid::Boid;
+use crate::camera::{Camera, Drag};
+use crate::cli::{cli, ArenaKind};
+use crate::config::ConfigWatcher;
 use crate::flock::Flock;
-use crate::keys::{key_pressed, key_released, Keybinds};
+use crate::glyph::{Glyph, Glyphs};
+use crate::keys::{key_from_name, key_pressed, key_released, Keybinds};
+use crate::preset::{library, load_presets, Preset};
+use crate::record::Recorder;
+use crate::scene::Scene;
+use crate::snapshot::{load_cli_snapshot, Snapshot};
+use crate::steering::Rule;
+use crate::trail::Trails;
 use crate::view;
-use crate::window::resized;
+use crate::window::{mouse_moved, mouse_pressed, mouse_released, mouse_wheel, resized};
+use crate::world::World;
 
-use nannou::prelude::{App, Draw, Rect};
+use nannou::prelude::{App, Draw, Key, Rect};
+use nannou::wgpu::Texture;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
 
-const INITIAL_BOIDS: usize = 1024;
+// How long what just happened is shown for
+const MESSAGE_TIME: Duration = Duration::from_secs(4);
 
 pub struct Model {
     pub flock: Vec<Boid>,
+    pub world: World,
     pub keybinds: Keybinds,
+    // The presets to switch between, and which one is in use, if any
+    pub presets: Vec<Preset>,
+    pub preset_index: Option<usize>,
+    // Keys from the config file, and the keys whose actions they do
+    pub keymap: HashMap<Key, Key>,
+    // Reloads the config file when it changes, if one was given
+    pub watcher: Option<ConfigWatcher>,
+    // What just happened, like where a file was saved, and when - printing it would end up in
+    // the middle of the terminal front end, so it is shown by both front ends instead
+    pub status: Option<(String, Instant)>,
+    // The arenas to switch between, and which one is in use
+    pub arenas: Vec<Arena>,
+    pub arena_index: usize,
+    // The quick-save slot F7 and F8 use
+    pub snapshot_slot: usize,
+    // The visible part of the world, which is independent of the size of the window
+    pub camera: Camera,
+    // The mouse drag moving the camera around, if there is one
+    pub drag: Option<Drag>,
+    // The id of the boid picked with the mouse
+    pub selected: Option<u64>,
+    pub trails: Trails,
+    pub glyphs: Glyphs,
+    pub recorder: Recorder,
     pub win_rect: Rect,
     pub draw: Draw,
 }
@@ -19,26 +60,276 @@ impl Model {
     // Our constructor
     pub fn new(app: &App) -> Self {
         // Creating the window
-        let _window = app
+        let mut window = app
             .new_window()
-            .title(format!("{INITIAL_BOIDS} boids!"))
+            .title(format!("{} boids!", cli().boids))
+            .size(cli().window_width, cli().window_height)
             // Functions to call at certain event
             .key_pressed(key_pressed)
             .key_released(key_released)
+            .mouse_pressed(mouse_pressed)
+            .mouse_released(mouse_released)
+            .mouse_moved(mouse_moved)
+            .mouse_wheel(mouse_wheel)
             .resized(resized)
-            .view(view)
-            .build()
-            .expect("Unable to build the app");
+            .view(view);
+        if cli().fullscreen {
+            window = window.fullscreen();
+        }
+        window.build().expect("Unable to build the app");
+
+        Self::build(app.window_rect(), load_sprite(app), app.draw())
+    }
+
+    // A model drawn somewhere other than a nannou window, like the terminal - which always shows
+    // the whole world, so the window is taken to be the size of the world, in pixels like a real
+    // one. That way recordings show the whole world too
+    pub fn without_window() -> Self {
+        Self::build(
+            Rect::from_w_h(cli().world_width, cli().world_height),
+            None,
+            Draw::new(),
+        )
+    }
+
+    fn build(win_rect: Rect, sprite: Option<Texture>, draw: Draw) -> Self {
+        // The world stays the same size whatever the window does
+        let world_rect = Rect::from_w_h(cli().world_width, cli().world_height);
 
-        // The window rect
-        let win_rect = app.window_rect();
+        let (presets, preset_index) = load_cli_presets();
+        let glyphs = load_glyphs(sprite);
+        let arenas = load_arenas();
+        let arena_index = cli_arena_index(&arenas);
 
         // Our model is the state of our application, which can be accessed from all functions
-        Self {
-            flock: Flock::new_flock(win_rect, INITIAL_BOIDS),
+        let world = new_world(world_rect, arenas[arena_index].clone());
+
+        let mut model = Self {
+            flock: Flock::new_flock(&world, cli().boids),
+            world,
             keybinds: Keybinds::default(),
+            presets,
+            preset_index,
+            keymap: load_keymap(&cli().file_config.keys),
+            watcher: cli()
+                .config
+                .clone()
+                .map(|path| ConfigWatcher::new(path, cli().file_config.clone())),
+            status: None,
+            arenas,
+            arena_index,
+            snapshot_slot: 0,
+            camera: Camera::fitting(world_rect, win_rect),
+            drag: None,
+            selected: None,
+            trails: Trails::default(),
+            glyphs,
+            recorder: Recorder::from_cli(),
             win_rect,
-            draw: app.draw(),
+            draw,
+        };
+        // Always remembered, so Shift+F5 can export the trails even when they aren't shown
+        model.trails.record = true;
+        model.apply_preset();
+        if let Some(snapshot) = load_cli_snapshot() {
+            model.restore_snapshot(snapshot);
+        }
+        model
+    }
+
+    // The boid picked with the mouse, if it is still alive
+    pub fn selected_boid(&self) -> Option<&Boid> {
+        let id = self.selected?;
+        self.flock.iter().find(|boid| boid.id() == id)
+    }
+
+    // What the renderers that don't go through nannou draw
+    pub fn scene(&self) -> Scene<'_> {
+        Scene {
+            world: &self.world,
+            flock: &self.flock,
+            trails: &self.trails,
+            glyphs: &self.glyphs,
+            keybinds: &self.keybinds,
+            selected: self.selected,
+        }
+    }
+
+    // Says what just happened, for a few seconds
+    pub fn say(&mut self, message: impl Into<String>) {
+        self.status = Some((message.into(), Instant::now()));
+    }
+
+    // What to show at the top of the window, or under the flock in the terminal - what went
+    // wrong with reloading the config file, and what just happened
+    pub fn message(&self) -> Option<String> {
+        let watcher = self
+            .watcher
+            .as_ref()
+            .and_then(|watcher| watcher.message.as_deref());
+        let status = self
+            .status
+            .as_ref()
+            .filter(|(_, said)| said.elapsed() < MESSAGE_TIME)
+            .map(|(message, _)| message.as_str());
+        let lines: Vec<&str> = watcher.into_iter().chain(status).collect();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    // Everything needed to carry on from here later
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::capture(&self.world, &self.flock, &self.trails, &self.glyphs)
+    }
+
+    // Carries on from a snapshot - showing all of the world again, if it changed size
+    pub fn restore_snapshot(&mut self, snapshot: Snapshot) {
+        let bounds = self.world.bounds;
+        let arena = snapshot.arena();
+        let index = snapshot.restore(
+            &mut self.world,
+            &mut self.flock,
+            &mut self.trails,
+            &mut self.glyphs,
+            &self.arenas,
+        );
+        self.trails.record = true;
+        if let Some(index) = index {
+            self.arena_index = index;
+        }
+        if self.world.arena.kind() != arena {
+            self.say("The snapshot's arena isn't loaded, so the rectangle is used instead");
+        }
+        if self.world.bounds != bounds {
+            self.camera.fit(self.world.bounds, self.win_rect);
+        }
+        if self.selected_boid().is_none() {
+            self.selected = None;
         }
     }
+
+    // Gives every boid the parameters of the current preset, if there is one
+    pub fn apply_preset(&mut self) {
+        if let Some(preset) = self.preset_index.and_then(|index| self.presets.get(index)) {
+            preset.apply(&mut self.flock, &mut self.world, &mut self.glyphs);
+        }
+    }
+}
+
+// The presets from the command line and the config file, followed by the library, and the one to
+// start with - a broken preset file shouldn't stop the app, so just say what went wrong
+pub fn load_cli_presets() -> (Vec<Preset>, Option<usize>) {
+    let mut presets = match &cli().preset {
+        Some(path) => load_presets(path).unwrap_or_else(|error| {
+            eprintln!("Unable to load presets from {}: {error}", path.display());
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    presets.extend(cli().file_config.presets.iter().cloned());
+    // Loaded presets are used straight away, but the library only when it is asked for
+    let mut index = (!presets.is_empty()).then_some(0);
+    presets.extend(library());
+
+    if let Some(name) = &cli().start_preset {
+        // Dashes can stand in for spaces, so names don't need quotes
+        let name = name.replace('-', " ");
+        index = presets
+            .iter()
+            .position(|preset| preset.name.eq_ignore_ascii_case(&name));
+        if index.is_none() {
+            eprintln!("There is no preset called {name}");
+        }
+    }
+    (presets, index)
+}
+
+// The keys bound in the config file - keys that can't be found are left out
+pub fn load_keymap(keys: &BTreeMap<String, String>) -> HashMap<Key, Key> {
+    keys.iter()
+        .filter_map(
+            |(key, action)| match (key_from_name(key), key_from_name(action)) {
+                (Some(key), Some(action)) => Some((key, action)),
+                _ => {
+                    eprintln!("Unable to bind {key} to {action}, since one of them isn't a key");
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+// A world set up by the command line
+pub fn new_world(bounds: Rect, arena: Arena) -> World {
+    let mut world = World::new(bounds);
+    world.arena = arena;
+    world.boundary = cli().boundary;
+    world.steering = cli().steering;
+    world.collisions = cli().collisions;
+    world.coloring.scheme = cli().color_scheme;
+    world.coloring.palette = cli().palette;
+    world.rules = cli().rules.clone().unwrap_or_else(|| Rule::ALL.to_vec());
+    world.parameters = cli().parameters();
+    world.foraging.sources = cli().food_sources;
+    world.foraging.regrowth = cli().food_regrowth;
+    world.alarms.delay = cli().alarm_delay;
+    world.alarms.decay = cli().alarm_decay;
+    world.species = cli().species;
+    world
+}
+
+// Where to start in the arenas from the command line - the arena that was asked for, if it
+// could be loaded
+pub fn cli_arena_index(arenas: &[Arena]) -> usize {
+    arenas
+        .iter()
+        .position(|arena| arena.kind() == cli().arena)
+        .unwrap_or(0)
+}
+
+// The arenas from the command line - the mask is only there if it could be loaded
+pub fn load_arenas() -> Vec<Arena> {
+    let polygon = cli()
+        .arena_polygon
+        .clone()
+        .map_or_else(Arena::star, Arena::Polygon);
+    let mut arenas = vec![Arena::Rect, Arena::Circle, polygon];
+    if let Some(path) = &cli().arena_mask {
+        match Mask::load(path) {
+            Ok(mask) => arenas.push(Arena::Mask(mask)),
+            Err(error) => eprintln!("Unable to load the arena from {}: {error}", path.display()),
+        }
+    } else if cli().arena == ArenaKind::Mask {
+        eprintln!("The mask arena needs an image, given with --arena-mask");
+    }
+    arenas
+}
+
+// The image for the sprite glyph, if one was given and could be loaded - it lives on the GPU, so
+// it needs the app
+fn load_sprite(app: &App) -> Option<Texture> {
+    let path = cli().sprite.as_ref()?;
+    Texture::from_path(app, path)
+        .map_err(|error| eprintln!("Unable to load the sprite from {}: {error}", path.display()))
+        .ok()
+}
+
+// The glyphs from the command line
+pub fn load_glyphs(sprite: Option<Texture>) -> Glyphs {
+    let mut glyph = cli().glyph;
+    if glyph == Glyph::Sprite && sprite.is_none() {
+        eprintln!("The sprite glyph needs an image, given with --sprite");
+        glyph = Glyph::Triangle;
+    }
+    Glyphs {
+        glyph,
+        // Without a list, every species gets its own glyph in order
+        species: cli().species_glyphs.clone().unwrap_or_else(|| {
+            Glyph::ALL
+                .into_iter()
+                .filter(|&glyph| glyph != Glyph::Sprite || sprite.is_some())
+                .collect()
+        }),
+        per_species: cli().species_glyphs.is_some(),
+        sprite,
+    }
 }
@@ -0,0 +1,219 @@
+use crate::arena::Arena;
+use crate::boid::{Boid, Parameters};
+use crate::cli::{cli, OptimiseArgs};
+use crate::collision::count_overlaps;
+use crate::flock::Flock;
+use crate::metrics::{mean_nearest_neighbour_distance, polarisation};
+use crate::model::{cli_arena_index, load_arenas, new_world};
+use crate::preset::{save_presets, Preset, PresetError};
+use crate::random::{random_range, random_seed, with_seed};
+
+use clap::ValueEnum;
+use nannou::prelude::Rect;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+
+// The best individuals of a generation are copied unchanged into the next one
+const ELITES: usize = 2;
+// The number of individuals competing to become a parent
+const TOURNAMENT_SIZE: usize = 3;
+// The fraction by which a parameter can change when mutated, in either direction
+const MUTATION: f32 = 0.2;
+
+// What the optimiser is trying to achieve
+#[derive(ValueEnum, Clone, Copy)]
+pub enum Fitness {
+    // Get the polarisation of the flock as close to the target as possible
+    Polarisation,
+    // Get the mean distance to the nearest neighbour as close to the target as possible
+    NeighbourDistance,
+    // Have as few overlapping boids as possible
+    NoCollisions,
+}
+
+impl Fitness {
+    // The target used when none is given
+    const fn default_target(self) -> f32 {
+        match self {
+            Self::Polarisation => 0.9,
+            Self::NeighbourDistance => 20.0,
+            Self::NoCollisions => 0.0,
+        }
+    }
+
+    // Scores a flock - higher is better, and 0.0 is perfect
+    fn score(self, flock: &[Boid], bounds: Rect, target: f32) -> f32 {
+        match self {
+            Self::Polarisation => -(polarisation(flock) - target).abs(),
+            Self::NeighbourDistance => {
+                -(mean_nearest_neighbour_distance(flock) - target).abs() / target.max(1.0)
+            }
+            Self::NoCollisions => {
+                -(count_overlaps(flock, bounds) as f32) / flock.len().max(1) as f32
+            }
+        }
+    }
+}
+
+// The evolved parameters, the rest stay at their defaults
+#[derive(Clone, Copy)]
+struct Genome {
+    max_force: f32,
+    alignment_mod: f32,
+    cohesion_mod: f32,
+    separation_mod: f32,
+}
+
+impl Genome {
+    // A random genome, between a quarter and four times the default values
+    fn random(defaults: &Parameters) -> Self {
+        let scale = || 4.0_f32.powf(random_range(-1.0, 1.0));
+        Self {
+            max_force: defaults.max_force * scale(),
+            alignment_mod: defaults.alignment_mod * scale(),
+            cohesion_mod: defaults.cohesion_mod * scale(),
+            separation_mod: defaults.separation_mod * scale(),
+        }
+    }
+
+    // Picks every gene from one of the two parents
+    fn crossover(&self, other: &Self) -> Self {
+        let pick = |a: f32, b: f32| if random_range(0.0, 1.0) < 0.5 { a } else { b };
+        Self {
+            max_force: pick(self.max_force, other.max_force),
+            alignment_mod: pick(self.alignment_mod, other.alignment_mod),
+            cohesion_mod: pick(self.cohesion_mod, other.cohesion_mod),
+            separation_mod: pick(self.separation_mod, other.separation_mod),
+        }
+    }
+
+    fn mutate(&self) -> Self {
+        let mutate = |value: f32| value * (1.0 + random_range(-MUTATION, MUTATION));
+        Self {
+            max_force: mutate(self.max_force),
+            alignment_mod: mutate(self.alignment_mod),
+            cohesion_mod: mutate(self.cohesion_mod),
+            separation_mod: mutate(self.separation_mod),
+        }
+    }
+
+    fn parameters(&self, defaults: &Parameters) -> Parameters {
+        Parameters {
+            max_force: self.max_force,
+            alignment_mod: self.alignment_mod,
+            cohesion_mod: self.cohesion_mod,
+            separation_mod: self.separation_mod,
+            ..*defaults
+        }
+    }
+}
+
+// Runs one simulation with the given parameters, in the world the command line and config set
+// up, and scores how it went
+fn evaluate(
+    parameters: &Parameters,
+    arena: &Arena,
+    args: &OptimiseArgs,
+    fitness: Fitness,
+    target: f32,
+) -> f32 {
+    let bounds = Rect::from_w_h(cli().world_width, cli().world_height);
+    let mut world = new_world(bounds, arena.clone());
+    world.parameters = *parameters;
+    let mut flock = Flock::new_flock(&world, args.boids);
+    for boid in &mut flock {
+        boid.set_parameters(parameters);
+    }
+
+    // Score the last quarter of the run, once the flock has had time to settle
+    let scored_from = args.steps - args.steps / 4;
+    let mut total = 0.0;
+    for step in 0..args.steps {
+        world.step(&mut flock);
+        if step >= scored_from {
+            total += fitness.score(&flock, bounds, target);
+        }
+    }
+    total / (args.steps - scored_from).max(1) as f32
+}
+
+// Picks the best of a few random individuals
+fn tournament(scored: &[(Genome, f32)]) -> &Genome {
+    (0..TOURNAMENT_SIZE)
+        .map(|_| &scored[random_range(0, scored.len())])
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(genome, _)| genome)
+        .expect("The tournament has at least one contestant")
+}
+
+// Evolves the parameters, and saves the best ones as presets
+pub fn run(args: &OptimiseArgs) -> Result<(), PresetError> {
+    let fitness = args.fitness;
+    let target = args.target.unwrap_or_else(|| fitness.default_target());
+    // Starting around the parameters given on the command line
+    let defaults = cli().parameters();
+    let population_size = args.population.max(ELITES + 1);
+    // Loaded once, rather than for every simulation
+    let arenas = load_arenas();
+    let arena = &arenas[cli_arena_index(&arenas)];
+
+    let mut population: Vec<Genome> = (0..population_size)
+        .map(|_| Genome::random(&defaults))
+        .collect();
+    let mut scored = Vec::new();
+
+    for generation in 0..args.generations.max(1) {
+        // Every simulation is independent, so they can all run at the same time - each with its
+        // own random numbers, seeded in order, so the same seed evolves the same way
+        let seeds: Vec<u64> = population.iter().map(|_| random_seed()).collect();
+        scored = population
+            .par_iter()
+            .zip(&seeds)
+            .map(|(genome, &seed)| {
+                let score = with_seed(seed, || {
+                    evaluate(&genome.parameters(&defaults), arena, args, fitness, target)
+                });
+                (*genome, score)
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        println!(
+            "Generation {}: best fitness {:.4}",
+            generation + 1,
+            scored[0].1
+        );
+
+        // The elites survive as they are, and the rest are bred from the current generation
+        population = scored
+            .iter()
+            .take(ELITES)
+            .map(|(genome, _)| *genome)
+            .collect();
+        while population.len() < population_size {
+            let child = tournament(&scored).crossover(tournament(&scored)).mutate();
+            population.push(child);
+        }
+    }
+
+    let presets: Vec<Preset> = scored
+        .iter()
+        .take(args.keep.max(1))
+        .enumerate()
+        .map(|(rank, (genome, score))| Preset {
+            name: format!("optimised-{}", rank + 1),
+            fitness: Some(*score),
+            color_scheme: None,
+            palette: None,
+            glyph: None,
+            parameters: genome.parameters(&defaults),
+            unknown: BTreeMap::new(),
+        })
+        .collect();
+    save_presets(&args.output, &presets)?;
+    println!(
+        "Saved the {} best parameter sets to {}",
+        presets.len(),
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use crate::boid::Boid;
+use crate::color::Color;
+
+use nannou::prelude::{Draw, Rect, Vec2};
+use rayon::prelude::*;
+
+// The size of a cell of the grid, in pixels
+const CELL_SIZE: f32 = 16.0;
+// The amount of pheromone which is drawn fully opaque
+const SATURATION: f32 = 10.0;
+
+// A scalar field over the world, which boids leave pheromone on as they fly, and which spreads
+// out and fades over time
+pub struct PheromoneGrid {
+    pub enabled: bool,
+    // The fraction of each cell that spreads to its neighbours every step
+    pub diffusion: f32,
+    // The fraction of each cell that disappears every step
+    pub evaporation: f32,
+    // How much pheromone each boid leaves behind every step
+    pub deposit: f32,
+    bounds: Rect,
+    cols: usize,
+    rows: usize,
+    values: Vec<f32>,
+}
+
+impl PheromoneGrid {
+    pub fn new(bounds: Rect) -> Self {
+        let cols = (bounds.w() / CELL_SIZE).ceil().max(1.0) as usize;
+        let rows = (bounds.h() / CELL_SIZE).ceil().max(1.0) as usize;
+        Self {
+            enabled: false,
+            diffusion: 0.1,
+            evaporation: 0.01,
+            deposit: 0.1,
+            bounds,
+            cols,
+            rows,
+            values: vec![0.0; cols * rows],
+        }
+    }
+
+    // Starts over with an empty grid covering the new bounds, if they changed
+    pub fn fit(&mut self, bounds: Rect) {
+        if self.bounds != bounds {
+            *self = Self {
+                enabled: self.enabled,
+                diffusion: self.diffusion,
+                evaporation: self.evaporation,
+                deposit: self.deposit,
+                ..Self::new(bounds)
+            };
+        }
+    }
+
+    // The amount of pheromone in every cell
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    // Puts back saved amounts - they only fit if the grid is the same size
+    pub fn set_values(&mut self, values: Vec<f32>) {
+        if values.len() == self.values.len() {
+            self.values = values;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.fill(0.0);
+    }
+
+    // The column and row of the cell a position is in - positions outside go in the edge cells
+    fn cell_of(&self, position: Vec2) -> (usize, usize) {
+        let cell = (position - self.bounds.bottom_left()) / CELL_SIZE;
+        (
+            (cell.x.max(0.0) as usize).min(self.cols - 1),
+            (cell.y.max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+
+    // The value of a cell, wrapping around the edges like the boids do
+    fn value(&self, col: isize, row: isize) -> f32 {
+        let col = col.rem_euclid(self.cols as isize) as usize;
+        let row = row.rem_euclid(self.rows as isize) as usize;
+        self.values[row * self.cols + col]
+    }
+
+    // The direction in which the pheromone increases the fastest, around a position
+    pub fn gradient(&self, position: Vec2) -> Vec2 {
+        let (col, row) = self.cell_of(position);
+        let (col, row) = (col as isize, row as isize);
+        Vec2::new(
+            self.value(col + 1, row) - self.value(col - 1, row),
+            self.value(col, row + 1) - self.value(col, row - 1),
+        ) / (2.0 * CELL_SIZE)
+    }
+
+    // Moves the grid one step forward - the boids leave pheromone, which then spreads and fades
+    pub fn step(&mut self, flock: &[Boid]) {
+        for boid in flock {
+            let (col, row) = self.cell_of(boid.position());
+            self.values[row * self.cols + col] += self.deposit;
+        }
+
+        let keep = 1.0 - self.diffusion;
+        let fade = 1.0 - self.evaporation;
+        let mut values = vec![0.0; self.values.len()];
+        values
+            .par_chunks_mut(self.cols)
+            .enumerate()
+            .for_each(|(row, values_row)| {
+                let row = row as isize;
+                for (col, value) in values_row.iter_mut().enumerate() {
+                    let col = col as isize;
+                    // Every cell keeps some of its own pheromone, and gets some from its neighbours
+                    let neighbours = self.value(col - 1, row)
+                        + self.value(col + 1, row)
+                        + self.value(col, row - 1)
+                        + self.value(col, row + 1);
+                    *value =
+                        (keep * self.value(col, row) + self.diffusion * neighbours / 4.0) * fade;
+                }
+            });
+        self.values = values;
+    }
+
+    // Draws the grid as a heatmap
+    pub fn show(&self, draw: &Draw) {
+        for (rect, color) in self.cells() {
+            draw.rect()
+                .xy(rect.xy())
+                .wh(rect.wh())
+                .rgba(color.r, color.g, color.b, color.a);
+        }
+    }
+
+    // The cells of the heatmap, and their colors - empty cells are skipped
+    pub fn cells(&self) -> Vec<(Rect, Color)> {
+        self.values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &value)| {
+                let alpha = (value / SATURATION).min(1.0) * 0.6;
+                if alpha < 0.01 {
+                    return None;
+                }
+                let cell = Vec2::new((index % self.cols) as f32, (index / self.cols) as f32);
+                let centre = self.bounds.bottom_left() + (cell + 0.5) * CELL_SIZE;
+                Some((
+                    Rect::from_xy_wh(centre, Vec2::splat(CELL_SIZE)),
+                    Color::new(1.0, 0.6, 0.1, alpha),
+                ))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,210 @@
+use crate::boid::{Boid, Parameters};
+use crate::color::{ColorScheme, Palette};
+use crate::config::check_preset;
+use crate::falloff::Falloff;
+use crate::glyph::{Glyph, Glyphs};
+use crate::world::World;
+
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// A named set of parameters for the boids
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Preset {
+    pub name: String,
+    // How well the parameters did, if they came from the optimiser
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fitness: Option<f32>,
+    // How the boids are colored - presets without one keep the current coloring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_scheme: Option<ColorScheme>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Palette>,
+    // What the boids are drawn as - presets without one keep the current glyph
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glyph: Option<Glyph>,
+    #[serde(flatten)]
+    pub parameters: Parameters,
+    // The keys that mean nothing - serde_ignored can't see past the flattened parameters, so
+    // they are caught here instead, to be warned about
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, IgnoredAny>,
+}
+
+impl Preset {
+    // The live values of the world and the first boid, as a preset
+    pub fn live(name: String, parameters: Parameters, world: &World, glyphs: &Glyphs) -> Self {
+        Self {
+            name,
+            fitness: None,
+            color_scheme: Some(world.coloring.scheme),
+            palette: Some(world.coloring.palette),
+            glyph: Some(glyphs.glyph),
+            parameters,
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    // Gives every boid the parameters, and new flocks too, and switches to the look of the
+    // preset, if it has one
+    pub fn apply(&self, flock: &mut [Boid], world: &mut World, glyphs: &mut Glyphs) {
+        for boid in flock {
+            boid.set_parameters(&self.parameters);
+        }
+        world.parameters = self.parameters;
+        if let Some(scheme) = self.color_scheme {
+            world.coloring.scheme = scheme;
+        }
+        if let Some(palette) = self.palette {
+            world.coloring.palette = palette;
+        }
+        if let Some(glyph) = self.glyph {
+            glyphs.glyph = glyph;
+        }
+    }
+}
+
+// The layout of a preset file - a list of [[preset]] tables
+#[derive(Serialize, Deserialize)]
+struct PresetFile {
+    #[serde(rename = "preset")]
+    presets: Vec<Preset>,
+}
+
+pub enum PresetError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Invalid(String),
+}
+
+impl Display for PresetError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Parse(error) => write!(f, "invalid preset file: {error}"),
+            Self::Serialize(error) => write!(f, "unable to write presets: {error}"),
+            Self::Invalid(message) => write!(f, "invalid preset file: {message}"),
+        }
+    }
+}
+
+// Loads the presets in a file, warning about the keys in it that mean nothing, and turning down
+// parameters the boids can't use
+pub fn load_presets(path: &Path) -> Result<Vec<Preset>, PresetError> {
+    let text = fs::read_to_string(path).map_err(PresetError::Io)?;
+    let deserializer = toml::Deserializer::parse(&text).map_err(PresetError::Parse)?;
+    let file: PresetFile = serde_ignored::deserialize(deserializer, |key| {
+        eprintln!("Ignoring the unknown setting {key} in {}", path.display());
+    })
+    .map_err(PresetError::Parse)?;
+    for (index, preset) in file.presets.iter().enumerate() {
+        for key in preset.unknown.keys() {
+            eprintln!(
+                "Ignoring the unknown setting preset.{index}.{key} in {}",
+                path.display()
+            );
+        }
+        check_preset(preset).map_err(PresetError::Invalid)?;
+    }
+    Ok(file.presets)
+}
+
+pub fn save_presets(path: &Path, presets: &[Preset]) -> Result<(), PresetError> {
+    let text = toml::to_string(&PresetFile {
+        presets: presets.to_vec(),
+    })
+    .map_err(PresetError::Serialize)?;
+    fs::write(path, text).map_err(PresetError::Io)
+}
+
+// The presets that are always there, after the ones that were loaded
+pub fn library() -> Vec<Preset> {
+    vec![
+        // Fast and tightly aligned, sweeping across the sky in waves
+        Preset {
+            name: String::from("murmuration"),
+            fitness: None,
+            color_scheme: Some(ColorScheme::HeadingHue),
+            palette: None,
+            glyph: Some(Glyph::Bird),
+            parameters: Parameters {
+                max_speed: 6.0,
+                max_force: 0.04,
+                perception_radius: 80.0,
+                alignment_mod: 1.2,
+                cohesion_mod: 0.9,
+                separation_mod: 1.0,
+                alignment_falloff: Falloff::Linear,
+                cohesion_falloff: Falloff::Constant,
+                separation_falloff: Falloff::InverseSquare,
+            },
+            unknown: BTreeMap::new(),
+        },
+        // Slower and evenly spaced, turning together
+        Preset {
+            name: String::from("fish school"),
+            fitness: None,
+            color_scheme: Some(ColorScheme::Speed),
+            palette: Some(Palette::Viridis),
+            glyph: Some(Glyph::Fish),
+            parameters: Parameters {
+                max_speed: 3.5,
+                max_force: 0.03,
+                perception_radius: 60.0,
+                alignment_mod: 1.0,
+                cohesion_mod: 1.0,
+                separation_mod: 1.2,
+                alignment_falloff: Falloff::Smoothstep,
+                cohesion_falloff: Falloff::Constant,
+                separation_falloff: Falloff::Inverse,
+            },
+            unknown: BTreeMap::new(),
+        },
+        // Barely aligned, swarming around the middle of their neighbours
+        Preset {
+            name: String::from("gnats"),
+            fitness: None,
+            color_scheme: Some(ColorScheme::Density),
+            palette: Some(Palette::Magma),
+            glyph: Some(Glyph::Circle),
+            parameters: Parameters {
+                max_speed: 4.0,
+                max_force: 0.08,
+                perception_radius: 30.0,
+                alignment_mod: 0.05,
+                cohesion_mod: 1.5,
+                separation_mod: 0.6,
+                alignment_falloff: Falloff::Constant,
+                cohesion_falloff: Falloff::Constant,
+                separation_falloff: Falloff::Inverse,
+            },
+            unknown: BTreeMap::new(),
+        },
+        // Strong cohesion, weak alignment and slow turns, which circle around an empty middle
+        Preset {
+            name: String::from("milling torus"),
+            fitness: None,
+            color_scheme: Some(ColorScheme::LocalAlignment),
+            palette: None,
+            glyph: Some(Glyph::Dart),
+            parameters: Parameters {
+                max_speed: 4.0,
+                max_force: 0.015,
+                perception_radius: 120.0,
+                alignment_mod: 0.3,
+                cohesion_mod: 1.2,
+                separation_mod: 1.0,
+                alignment_falloff: Falloff::Gaussian,
+                cohesion_falloff: Falloff::Linear,
+                separation_falloff: Falloff::Inverse,
+            },
+            unknown: BTreeMap::new(),
+        },
+    ]
+}
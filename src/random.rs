@@ -0,0 +1,87 @@
+use nannou::rand::distributions::uniform::SampleUniform;
+use nannou::rand::{random, Error, Rng, RngCore};
+use std::cell::Cell;
+use std::sync::Mutex;
+
+// The random numbers of the whole simulation come from here, so a run can be repeated by giving
+// it the same seed - it starts out unseeded, and gets a random seed the first time it is used
+static RNG: Mutex<Option<SplitMix64>> = Mutex::new(None);
+
+thread_local! {
+    // A generator for just this thread, used instead of the shared one while it is set
+    static LOCAL: Cell<Option<SplitMix64>> = const { Cell::new(None) };
+}
+
+// A small and fast generator, whose whole state is one number
+#[derive(Clone, Copy)]
+struct SplitMix64 {
+    state: u64,
+}
+
+impl RngCore for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// Runs a function with the generator, seeding it first if it hasn't been
+fn with_rng<T>(f: impl FnOnce(&mut SplitMix64) -> T) -> T {
+    if let Some(mut rng) = LOCAL.get() {
+        let result = f(&mut rng);
+        LOCAL.set(Some(rng));
+        return result;
+    }
+    let mut rng = RNG.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(rng.get_or_insert_with(|| SplitMix64 { state: random() }))
+}
+
+// Starts the random numbers over from a seed
+pub fn seed(seed: u64) {
+    *RNG.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(SplitMix64 { state: seed });
+}
+
+// Runs a function with random numbers of its own, starting from a seed - so work done in
+// parallel gets the same numbers whichever thread does it, and in whatever order
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    let outer = LOCAL.replace(Some(SplitMix64 { state: seed }));
+    let result = f();
+    LOCAL.set(outer);
+    result
+}
+
+// A seed for with_seed, taken from the random numbers - so a seeded run seeds the same way
+pub fn random_seed() -> u64 {
+    with_rng(SplitMix64::next_u64)
+}
+
+// Where the random numbers are - seeding with it carries on from there
+pub fn state() -> u64 {
+    with_rng(|rng| rng.state)
+}
+
+// A random value from min up to, but not including, max - like nannou's random_range, min and
+// max are swapped if they are the wrong way round
+pub fn random_range<T: PartialOrd + SampleUniform>(min: T, max: T) -> T {
+    let (min, max) = if min <= max { (min, max) } else { (max, min) };
+    with_rng(|rng| rng.gen_range(min..max))
+}
@@ -0,0 +1,308 @@
+use crate::camera::Camera;
+use crate::color::{Color, BACKGROUND};
+use crate::export::ExportError;
+use crate::glyph::Shape;
+use crate::scene::Scene;
+
+use nannou::image::RgbaImage;
+use nannou::prelude::{Rect, Vec2};
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Stroke, Transform};
+
+// The size of the heads of the force arrows, like in Boid::show_forces
+const ARROW_HEAD_LENGTH: f32 = 3.0;
+const ARROW_HEAD_WIDTH: f32 = 2.0;
+
+// Draws a scene on the CPU, the same way update::view draws it in the window - apart from the
+// text, and sprites, which are drawn as triangles. The whole world is fitted in the image.
+pub fn render(scene: &Scene, width: u32, height: u32) -> Result<RgbaImage, ExportError> {
+    let camera = Camera::fitting(
+        scene.world.bounds,
+        Rect::from_w_h(width as f32, height as f32),
+    );
+    render_view(scene, width, height, &camera)
+}
+
+// Draws what a camera sees, with the zoom in pixels of the image
+pub fn render_view(
+    scene: &Scene,
+    width: u32,
+    height: u32,
+    camera: &Camera,
+) -> Result<RgbaImage, ExportError> {
+    let pixmap = Pixmap::new(width, height).ok_or(ExportError::Size(width, height))?;
+    let mut painter = Painter::new(pixmap, camera);
+    painter.paint(scene);
+    Ok(painter.into_image())
+}
+
+// A pixmap, and the transform from the world to its pixels
+struct Painter {
+    pixmap: Pixmap,
+    transform: Transform,
+}
+
+impl Painter {
+    fn new(pixmap: Pixmap, camera: &Camera) -> Self {
+        // Pixmaps start at the top left, and go down - the world starts in the middle, and goes up
+        let centre = Vec2::new(pixmap.width() as f32, pixmap.height() as f32) / 2.0;
+        let zoom = camera.zoom;
+        let transform = Transform::from_row(
+            zoom,
+            0.0,
+            0.0,
+            -zoom,
+            centre.x - zoom * camera.position.x,
+            centre.y + zoom * camera.position.y,
+        );
+        Self { pixmap, transform }
+    }
+
+    // Draws everything in the same order as update::view
+    fn paint(&mut self, scene: &Scene) {
+        let world = scene.world;
+        let palette = world.coloring.palette;
+        let [r, g, b, a] = BACKGROUND.to_rgba8();
+        self.pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+
+        // The pheromones and the heatmaps go below everything else
+        let mut cells = Vec::new();
+        if world.pheromones.enabled {
+            cells.extend(world.pheromones.cells());
+        }
+        if world.density.show_occupancy {
+            cells.extend(world.density.occupancy_cells(palette));
+        }
+        if world.density.show_density {
+            cells.extend(world.density.density_cells(palette));
+        }
+        for (rect, color) in cells {
+            self.fill_rect(rect, color);
+        }
+
+        // The walls of the arena
+        let wall = Color::new(1.0, 1.0, 1.0, 0.3);
+        if let Some(outline) = world.arena.outline(world.bounds) {
+            self.stroke(&outline, true, 2.0, wall);
+        }
+        for rect in world.arena.wall_rects(world.bounds) {
+            self.fill_rect(rect, Color::new(1.0, 1.0, 1.0, 0.15));
+        }
+
+        for food in &world.foraging.food {
+            self.fill_circle(food.position, food.radius, food.color());
+        }
+
+        if scene.keybinds.highlight_first {
+            if let Some(boid) = scene.flock.first() {
+                self.show_perception(boid.position(), boid.perception_radius(), 0.025);
+            }
+        }
+
+        for boid in scene.flock {
+            if scene.trails.enabled {
+                let length = scene.trails.length.max(2);
+                let color = boid.color();
+                let mut age = 0;
+                for segment in scene.trails.segments(boid, length) {
+                    // Every piece of the line gets its own alpha, so the trail fades out
+                    for pair in segment.windows(2) {
+                        age += 1;
+                        let alpha = scene.trails.alpha * age as f32 / length as f32;
+                        self.stroke(pair, false, 1.0, Color { a: alpha, ..color });
+                    }
+                    age += 1;
+                }
+            }
+            for shape in boid.outline(scene.glyphs.of(boid.species())) {
+                match shape {
+                    Shape::Fill(points) => self.fill(&points, boid.color()),
+                    Shape::Stroke(points) => self.stroke(&points, false, 1.0, boid.color()),
+                }
+            }
+            if scene.keybinds.highlight_all {
+                self.show_perception(boid.position(), boid.perception_radius(), 0.0025);
+            }
+        }
+
+        if scene.keybinds.show_forces {
+            for boid in scene.flock {
+                for (vector, color) in boid.force_arrows() {
+                    if vector.length() >= f32::EPSILON {
+                        self.arrow(boid.position(), boid.position() + vector, color);
+                    }
+                }
+            }
+        }
+
+        if let Some(selected) = scene.selected_boid() {
+            self.show_perception(selected.position(), selected.perception_radius(), 0.05);
+            let highlight = Color::new(1.0, 1.0, 1.0, 0.8);
+            for other in scene.flock {
+                if selected.is_neighbour(other) {
+                    if scene.keybinds.show_forces {
+                        self.stroke(
+                            &[selected.position(), other.position()],
+                            false,
+                            1.0,
+                            Color::new(1.0, 1.0, 1.0, 0.3),
+                        );
+                    }
+                    self.stroke_circle(other.position(), other.diameter(), 1.5, highlight);
+                }
+            }
+            self.stroke_circle(selected.position(), selected.diameter(), 1.5, highlight);
+        }
+    }
+
+    fn show_perception(&mut self, centre: Vec2, radius: f32, alpha: f32) {
+        self.fill_circle(
+            centre,
+            radius,
+            Color::new(1.0, 1.0, 1.0, alpha.clamp(0.0, 1.0)),
+        );
+    }
+
+    fn fill(&mut self, points: &[Vec2], color: Color) {
+        if let Some(path) = polygon(points, true) {
+            self.pixmap.fill_path(
+                &path,
+                &paint(color),
+                FillRule::Winding,
+                self.transform,
+                None,
+            );
+        }
+    }
+
+    fn stroke(&mut self, points: &[Vec2], closed: bool, weight: f32, color: Color) {
+        if let Some(path) = polygon(points, closed) {
+            let stroke = Stroke {
+                width: weight,
+                ..Stroke::default()
+            };
+            self.pixmap
+                .stroke_path(&path, &paint(color), &stroke, self.transform, None);
+        }
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.fill(
+            &[
+                rect.bottom_left(),
+                rect.bottom_right(),
+                rect.top_right(),
+                rect.top_left(),
+            ],
+            color,
+        );
+    }
+
+    fn fill_circle(&mut self, centre: Vec2, radius: f32, color: Color) {
+        if let Some(path) = PathBuilder::from_circle(centre.x, centre.y, radius) {
+            self.pixmap.fill_path(
+                &path,
+                &paint(color),
+                FillRule::Winding,
+                self.transform,
+                None,
+            );
+        }
+    }
+
+    fn stroke_circle(&mut self, centre: Vec2, radius: f32, weight: f32, color: Color) {
+        if let Some(path) = PathBuilder::from_circle(centre.x, centre.y, radius) {
+            let stroke = Stroke {
+                width: weight,
+                ..Stroke::default()
+            };
+            self.pixmap
+                .stroke_path(&path, &paint(color), &stroke, self.transform, None);
+        }
+    }
+
+    // A line with a triangle at the end
+    fn arrow(&mut self, start: Vec2, end: Vec2, color: Color) {
+        let direction = (end - start).normalize_or_zero();
+        let base = end - direction * ARROW_HEAD_LENGTH;
+        let side = direction.perp() * ARROW_HEAD_WIDTH / 2.0;
+        self.stroke(&[start, base], false, 1.0, color);
+        self.fill(&[end, base + side, base - side], color);
+    }
+
+    // The pixels, without the premultiplied alpha tiny-skia keeps them in
+    fn into_image(self) -> RgbaImage {
+        let (width, height) = (self.pixmap.width(), self.pixmap.height());
+        let data = self
+            .pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let pixel = pixel.demultiply();
+                [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+            })
+            .collect();
+        RgbaImage::from_raw(width, height, data).expect("The pixmap has four bytes per pixel")
+    }
+}
+
+// A path through the points - there is no path with fewer than two points
+fn polygon(points: &[Vec2], closed: bool) -> Option<tiny_skia::Path> {
+    let (first, rest) = points.split_first()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(first.x, first.y);
+    for point in rest {
+        builder.line_to(point.x, point.y);
+    }
+    if closed {
+        builder.close();
+    }
+    builder.finish()
+}
+
+fn paint(color: Color) -> Paint<'static> {
+    let [r, g, b, a] = color.to_rgba8();
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boid::Boid;
+    use crate::glyph::Glyphs;
+    use crate::keys::Keybinds;
+    use crate::trail::Trails;
+    use crate::world::World;
+
+    fn render_flock(flock: &[Boid], width: u32, height: u32) -> Result<RgbaImage, ExportError> {
+        let scene = Scene {
+            world: &World::new(Rect::from_w_h(200.0, 100.0)),
+            flock,
+            trails: &Trails::default(),
+            glyphs: &Glyphs::default(),
+            keybinds: &Keybinds::default(),
+            selected: None,
+        };
+        render(&scene, width, height)
+    }
+
+    #[test]
+    fn render_makes_an_image_of_the_given_size() {
+        let flock = [Boid::new(Vec2::ZERO, Vec2::X)];
+        let image = render_flock(&flock, 64, 48).unwrap_or_else(|_| panic!("Unable to render"));
+        assert_eq!(image.dimensions(), (64, 48));
+        // Nothing is near the corners, so they are just the background
+        assert_eq!(image.get_pixel(0, 0).0, BACKGROUND.to_rgba8());
+        assert_eq!(image.get_pixel(63, 47).0, BACKGROUND.to_rgba8());
+    }
+
+    #[test]
+    fn render_turns_down_empty_images() {
+        assert!(matches!(
+            render_flock(&[], 0, 48),
+            Err(ExportError::Size(0, 48))
+        ));
+    }
+}
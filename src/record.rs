@@ -0,0 +1,231 @@
+use crate::camera::Camera;
+use crate::cli::cli;
+use crate::export::ExportError;
+use crate::raster::render_view;
+use crate::scene::Scene;
+
+use clap::ValueEnum;
+use nannou::image::codecs::gif::{GifEncoder, Repeat};
+use nannou::image::{self, Delay, Frame, RgbaImage};
+use nannou::prelude::Rect;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+// What the recorded frames get put together into, once the recording stops
+#[derive(ValueEnum, PartialEq, Clone, Copy)]
+pub enum Animation {
+    // Only the numbered PNGs
+    None,
+    Gif,
+    // An animated PNG, which keeps every color, unlike a GIF
+    Apng,
+}
+
+// Captures steps of the simulation as a numbered PNG sequence, drawn with the CPU rasteriser so
+// it works the same with and without a window - the frames are saved, and put together into the
+// animation, on another thread, so the simulation doesn't stop for them
+pub struct Recorder {
+    pub recording: bool,
+    directory: PathBuf,
+    width: u32,
+    height: u32,
+    // Only every nth step is captured
+    every: usize,
+    // The recording stops by itself after this many frames, if there is a limit
+    frames: Option<usize>,
+    // How long each frame is shown in the animation, in milliseconds
+    delay: u32,
+    animation: Animation,
+    // The number of steps since the recording started
+    steps: usize,
+    // The number of frames captured since the recording started
+    captured: usize,
+    // Where the frames of the recording go, while it is going
+    sender: Option<Sender<RgbaImage>>,
+    // The thread saving the frames - it says what it saved once it is done
+    worker: Option<JoinHandle<Result<String, ExportError>>>,
+}
+
+impl Recorder {
+    // A recorder set up by the command line
+    pub fn from_cli() -> Self {
+        Self {
+            recording: false,
+            directory: cli().record_dir.clone(),
+            width: cli().record_width,
+            height: cli().record_height,
+            every: cli().record_every as usize,
+            frames: cli().record_frames,
+            delay: cli().frame_delay,
+            animation: cli().animation,
+            steps: 0,
+            captured: 0,
+            sender: None,
+            worker: None,
+        }
+    }
+
+    // The number of steps between frames
+    pub const fn every(&self) -> usize {
+        self.every
+    }
+
+    // The number of frames captured since the recording started
+    pub const fn captured(&self) -> usize {
+        self.captured
+    }
+
+    // The size of the frames, in pixels
+    pub fn frame_rect(&self) -> Rect {
+        Rect::from_w_h(self.width as f32, self.height as f32)
+    }
+
+    // Stops the recording by itself after this many frames
+    pub fn limit_frames(&mut self, frames: usize) {
+        self.frames = Some(frames);
+    }
+
+    pub fn start(&mut self) -> Result<(), ExportError> {
+        // The last recording has to be done with the frames before they are written over
+        if let Some(result) = self.wait() {
+            result?;
+        }
+        fs::create_dir_all(&self.directory)?;
+        let (sender, receiver) = mpsc::channel();
+        let directory = self.directory.clone();
+        let (animation, delay, width, height) =
+            (self.animation, self.delay, self.width, self.height);
+        self.worker = Some(thread::spawn(move || {
+            save_frames(&receiver, &directory, animation, delay, width, height)
+        }));
+        self.sender = Some(sender);
+        self.recording = true;
+        self.steps = 0;
+        self.captured = 0;
+        Ok(())
+    }
+
+    // Stops recording - the animation is put together on the worker thread, so check on it
+    // with finished, or wait for it
+    pub fn stop(&mut self) {
+        self.recording = false;
+        // Closing the channel tells the worker there are no more frames
+        self.sender = None;
+    }
+
+    // What the worker saved, or what went wrong, once it is done - without waiting for it
+    pub fn finished(&mut self) -> Option<Result<String, ExportError>> {
+        if self.worker.as_ref().is_some_and(JoinHandle::is_finished) {
+            self.wait()
+        } else {
+            None
+        }
+    }
+
+    // Waits for the worker to be done, and says what it saved
+    pub fn wait(&mut self) -> Option<Result<String, ExportError>> {
+        let worker = self.worker.take()?;
+        Some(worker.join().unwrap_or_else(|_| {
+            Err(ExportError::Io(io::Error::other(
+                "the recording thread panicked",
+            )))
+        }))
+    }
+
+    // Captures the scene, as the camera sees it, if this is one of the steps being recorded -
+    // returns whether the recording has all its frames, and should be stopped
+    pub fn capture(&mut self, scene: &Scene, camera: &Camera) -> Result<bool, ExportError> {
+        if self.steps.is_multiple_of(self.every) {
+            let image = render_view(scene, self.width, self.height, camera)?;
+            // The worker only hangs up if it couldn't save a frame, which it says when it's done
+            let sent = self
+                .sender
+                .as_ref()
+                .is_some_and(|sender| sender.send(image).is_ok());
+            if !sent {
+                return Ok(true);
+            }
+            self.captured += 1;
+        }
+        self.steps += 1;
+        Ok(self.frames.is_some_and(|frames| self.captured >= frames))
+    }
+}
+
+// Saves the frames as they come, then puts them together into the animation once there are no
+// more, and says what it saved
+fn save_frames(
+    receiver: &Receiver<RgbaImage>,
+    directory: &Path,
+    animation: Animation,
+    delay: u32,
+    width: u32,
+    height: u32,
+) -> Result<String, ExportError> {
+    let mut frames = Vec::new();
+    for image in receiver {
+        let path = directory.join(format!("frame-{:06}.png", frames.len()));
+        image.save(&path)?;
+        frames.push(path);
+    }
+    let path = match animation {
+        _ if frames.is_empty() => return Ok(String::from("Nothing was recorded")),
+        Animation::None => directory.to_path_buf(),
+        Animation::Gif => {
+            let path = directory.join("animation.gif");
+            write_gif(&path, &frames, delay)?;
+            path
+        }
+        Animation::Apng => {
+            let path = directory.join("animation.png");
+            write_apng(&path, &frames, delay, width, height)?;
+            path
+        }
+    };
+    Ok(format!(
+        "Saved {} frames to {}",
+        frames.len(),
+        path.display()
+    ))
+}
+
+// Reads the frames back one at a time, so a long recording doesn't have to fit in memory
+fn write_gif(path: &Path, frames: &[PathBuf], delay: u32) -> Result<(), ExportError> {
+    let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+    encoder.set_repeat(Repeat::Infinite)?;
+    for frame in frames {
+        let image = image::open(frame)?.to_rgba8();
+        encoder.encode_frame(Frame::from_parts(
+            image,
+            0,
+            0,
+            Delay::from_numer_denom_ms(delay, 1),
+        ))?;
+    }
+    Ok(())
+}
+
+fn write_apng(
+    path: &Path,
+    frames: &[PathBuf],
+    delay: u32,
+    width: u32,
+    height: u32,
+) -> Result<(), ExportError> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Zero plays loops forever
+    encoder.set_animated(frames.len() as u32, 0)?;
+    encoder.set_frame_delay(delay.min(u16::MAX as u32) as u16, 1000)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        let image = image::open(frame)?.to_rgba8();
+        writer.write_image_data(image.as_raw())?;
+    }
+    writer.finish()?;
+    Ok(())
+}
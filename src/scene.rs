@@ -0,0 +1,24 @@
+use crate::boid::Boid;
+use crate::glyph::Glyphs;
+use crate::keys::Keybinds;
+use crate::trail::Trails;
+use crate::world::World;
+
+// Everything that gets drawn, borrowed from the model or from a headless run, so the renderers
+// that don't go through nannou can draw the same things as the window
+pub struct Scene<'a> {
+    pub world: &'a World,
+    pub flock: &'a [Boid],
+    pub trails: &'a Trails,
+    pub glyphs: &'a Glyphs,
+    pub keybinds: &'a Keybinds,
+    pub selected: Option<u64>,
+}
+
+impl Scene<'_> {
+    // The selected boid, if it is still alive
+    pub fn selected_boid(&self) -> Option<&Boid> {
+        let id = self.selected?;
+        self.flock.iter().find(|boid| boid.id() == id)
+    }
+}
@@ -0,0 +1,351 @@
+use crate::alarm::Alarms;
+use crate::arena::{Arena, Boundary};
+use crate::boid::{next_id, set_next_id, Boid, Parameters};
+use crate::cli::{cli, ArenaKind};
+use crate::color::Coloring;
+use crate::ecology::Ecology;
+use crate::food::Foraging;
+use crate::glyph::{Glyph, Glyphs};
+use crate::pheromone::PheromoneGrid;
+use crate::random;
+use crate::steering::{Rule, SteeringMode};
+use crate::trail::Trails;
+use crate::world::World;
+
+use nannou::prelude::{Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// The version of the snapshot format - it goes up whenever old snapshots can't be read anymore
+const SNAPSHOT_VERSION: u32 = 1;
+// The number of quick-save slots
+pub const SNAPSHOT_SLOTS: usize = 4;
+
+// Everything needed to carry on a simulation exactly where it was left
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    // The state of the random numbers, so the run goes on as it would have
+    rng: u64,
+    next_id: u64,
+    world: WorldState,
+    flock: Vec<Boid>,
+    trails: Trails,
+    glyph: Glyph,
+    per_species: bool,
+}
+
+// The world, without what is only drawn, like whether the heatmaps are shown
+#[derive(Serialize, Deserialize)]
+struct WorldState {
+    width: f32,
+    height: f32,
+    arena: ArenaKind,
+    // The corners of the polygon arena, so it can be shared without the command line that made it
+    polygon: Option<Vec<Vec2>>,
+    boundary: Boundary,
+    steering: SteeringMode,
+    rules: Vec<Rule>,
+    collisions: bool,
+    ecology: Ecology,
+    foraging: Foraging,
+    pheromones: PheromoneState,
+    alarms: Alarms,
+    // Where the boids have been, for the heatmaps - older snapshots start them over
+    #[serde(default)]
+    density: DensityState,
+    coloring: Coloring,
+    species: u8,
+    parameters: Parameters,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PheromoneState {
+    enabled: bool,
+    diffusion: f32,
+    evaporation: f32,
+    deposit: f32,
+    values: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct DensityState {
+    density: Vec<f32>,
+    occupancy: Vec<f32>,
+    steps: u64,
+}
+
+// Just the version, so snapshots from other versions can be turned down before reading the rest
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+pub enum SnapshotError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    Version(u32),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Parse(error) => write!(f, "invalid snapshot: {error}"),
+            Self::Serialize(error) => write!(f, "unable to write the snapshot: {error}"),
+            Self::Version(version) => write!(
+                f,
+                "the snapshot is version {version}, but only version {SNAPSHOT_VERSION} can be read"
+            ),
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn capture(world: &World, flock: &[Boid], trails: &Trails, glyphs: &Glyphs) -> Self {
+        let pheromones = &world.pheromones;
+        let (density, occupancy, steps) = world.density.counts();
+        Self {
+            version: SNAPSHOT_VERSION,
+            rng: random::state(),
+            next_id: next_id(),
+            world: WorldState {
+                width: world.bounds.w(),
+                height: world.bounds.h(),
+                arena: world.arena.kind(),
+                polygon: match &world.arena {
+                    Arena::Polygon(points) => Some(points.clone()),
+                    _ => None,
+                },
+                boundary: world.boundary,
+                steering: world.steering,
+                rules: world.rules.clone(),
+                collisions: world.collisions,
+                ecology: world.ecology.clone(),
+                foraging: world.foraging.clone(),
+                pheromones: PheromoneState {
+                    enabled: pheromones.enabled,
+                    diffusion: pheromones.diffusion,
+                    evaporation: pheromones.evaporation,
+                    deposit: pheromones.deposit,
+                    values: pheromones.values().to_vec(),
+                },
+                alarms: world.alarms.clone(),
+                density: DensityState {
+                    density: density.to_vec(),
+                    occupancy: occupancy.to_vec(),
+                    steps,
+                },
+                coloring: world.coloring,
+                species: world.species,
+                parameters: world.parameters,
+            },
+            flock: flock.to_vec(),
+            trails: trails.clone(),
+            glyph: glyphs.glyph,
+            per_species: glyphs.per_species,
+        }
+    }
+
+    // The kind of arena the snapshot was taken in
+    pub const fn arena(&self) -> ArenaKind {
+        self.world.arena
+    }
+
+    // Puts the simulation back the way it was - the arena is picked from the ones that are
+    // loaded, unless it is a polygon, and its index is returned. If it isn't loaded, the
+    // rectangle is used instead
+    pub fn restore(
+        self,
+        world: &mut World,
+        flock: &mut Vec<Boid>,
+        trails: &mut Trails,
+        glyphs: &mut Glyphs,
+        arenas: &[Arena],
+    ) -> Option<usize> {
+        let state = self.world;
+        let bounds = Rect::from_w_h(state.width, state.height);
+        let index = arenas.iter().position(|arena| arena.kind() == state.arena);
+        world.arena = match (state.polygon, index) {
+            (Some(points), _) => Arena::Polygon(points),
+            (None, Some(index)) => arenas[index].clone(),
+            (None, None) => Arena::Rect,
+        };
+        world.bounds = bounds;
+        world.boundary = state.boundary;
+        world.steering = state.steering;
+        world.rules = state.rules;
+        world.collisions = state.collisions;
+        world.ecology = state.ecology;
+        world.foraging = state.foraging;
+        world.pheromones = PheromoneGrid::new(bounds);
+        world.pheromones.enabled = state.pheromones.enabled;
+        world.pheromones.diffusion = state.pheromones.diffusion;
+        world.pheromones.evaporation = state.pheromones.evaporation;
+        world.pheromones.deposit = state.pheromones.deposit;
+        world.pheromones.set_values(state.pheromones.values);
+        world.alarms = state.alarms;
+        world.density.fit(bounds);
+        world.density.set_counts(
+            state.density.density,
+            state.density.occupancy,
+            state.density.steps,
+        );
+        world.coloring = state.coloring;
+        world.species = state.species;
+        world.parameters = state.parameters;
+
+        *flock = self.flock;
+        *trails = self.trails;
+        if self.glyph != Glyph::Sprite || glyphs.sprite.is_some() {
+            glyphs.glyph = self.glyph;
+        }
+        glyphs.per_species = self.per_species;
+        random::seed(self.rng);
+        set_next_id(self.next_id);
+        index
+    }
+}
+
+pub fn save_snapshot(path: &Path, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(SnapshotError::Io)?;
+    }
+    let text = ron::to_string(snapshot).map_err(SnapshotError::Serialize)?;
+    fs::write(path, text).map_err(SnapshotError::Io)
+}
+
+pub fn load_snapshot(path: &Path) -> Result<Snapshot, SnapshotError> {
+    let text = fs::read_to_string(path).map_err(SnapshotError::Io)?;
+    let Version { version } = ron::from_str(&text).map_err(SnapshotError::Parse)?;
+    if version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::Version(version));
+    }
+    ron::from_str(&text).map_err(SnapshotError::Parse)
+}
+
+// The snapshot to start from, if the command line gives one - a broken snapshot shouldn't stop the
+// app, so just say what went wrong and start a new flock
+pub fn load_cli_snapshot() -> Option<Snapshot> {
+    let path = cli().snapshot.as_ref()?;
+    load_snapshot(path)
+        .map_err(|error| {
+            eprintln!(
+                "Unable to load the snapshot from {}: {error}",
+                path.display()
+            )
+        })
+        .ok()
+}
+
+// The file of a quick-save slot, counting from 1 like the slot shown in the current values
+pub fn slot_path(slot: usize) -> PathBuf {
+    cli().snapshot_dir.join(format!("slot-{}.ron", slot + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A path no other test writes to
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("boids-rs-{}-{name}.ron", std::process::id()))
+    }
+
+    fn capture() -> Snapshot {
+        let mut world = World::new(Rect::from_w_h(300.0, 200.0));
+        world.species = 2;
+        world.collisions = true;
+        world.boundary = Boundary::Bounce;
+        let flock = [
+            Boid::new(Vec2::new(10.0, 20.0), Vec2::X),
+            Boid::new(Vec2::new(-50.0, 0.0), Vec2::Y),
+        ];
+        let mut trails = Trails::default();
+        trails.enabled = true;
+        for _ in 0..2 {
+            trails.push(&flock);
+            world.density.step(&flock);
+        }
+        Snapshot::capture(&world, &flock, &trails, &Glyphs::default())
+    }
+
+    fn to_text(snapshot: &Snapshot) -> String {
+        ron::to_string(snapshot).expect("Snapshots can be written")
+    }
+
+    #[test]
+    fn snapshots_load_the_same_as_they_were_saved() {
+        let snapshot = capture();
+        let path = temp_path("round-trip");
+        let loaded = save_snapshot(&path, &snapshot).and_then(|()| load_snapshot(&path));
+        let _ = fs::remove_file(&path);
+        let loaded = loaded.unwrap_or_else(|error| panic!("{error}"));
+        assert_eq!(to_text(&loaded), to_text(&snapshot));
+    }
+
+    #[test]
+    fn restoring_puts_the_world_back() {
+        let snapshot = capture();
+        let flock = snapshot.flock.clone();
+        // Through a file, so nothing is left out of what is saved
+        let path = temp_path("restore");
+        let loaded = save_snapshot(&path, &snapshot).and_then(|()| load_snapshot(&path));
+        let _ = fs::remove_file(&path);
+        let snapshot = loaded.unwrap_or_else(|error| panic!("{error}"));
+        let mut world = World::new(Rect::from_w_h(10.0, 10.0));
+        let mut restored = Vec::new();
+        let mut trails = Trails::default();
+        let index = snapshot.restore(
+            &mut world,
+            &mut restored,
+            &mut trails,
+            &mut Glyphs::default(),
+            &[Arena::Rect],
+        );
+        assert_eq!(index, Some(0));
+        assert_eq!(world.bounds.w(), 300.0);
+        assert_eq!(world.bounds.h(), 200.0);
+        assert_eq!(world.species, 2);
+        assert!(world.collisions);
+        assert!(world.boundary == Boundary::Bounce);
+        assert!(restored == flock);
+        assert!(trails.enabled);
+        assert_eq!(
+            trails.segments(&restored[0], 8),
+            [[Vec2::new(10.0, 20.0); 2]]
+        );
+        let (density, occupancy, steps) = world.density.counts();
+        assert_eq!(density.iter().sum::<f32>(), 2.0);
+        assert_eq!(occupancy.iter().sum::<f32>(), 4.0);
+        assert_eq!(steps, 2);
+    }
+
+    #[test]
+    fn snapshots_of_other_versions_are_turned_down() {
+        let text =
+            to_text(&capture()).replacen(&format!("version:{SNAPSHOT_VERSION}"), "version:99", 1);
+        let path = temp_path("version");
+        fs::write(&path, text).expect("Unable to write the test snapshot");
+        let loaded = load_snapshot(&path);
+        let _ = fs::remove_file(&path);
+        assert!(matches!(loaded, Err(SnapshotError::Version(99))));
+    }
+
+    #[test]
+    fn broken_snapshots_are_turned_down() {
+        let path = temp_path("broken");
+        fs::write(&path, "(version: 1, rng: \"lots\")").expect("Unable to write the test snapshot");
+        let loaded = load_snapshot(&path);
+        let _ = fs::remove_file(&path);
+        assert!(matches!(loaded, Err(SnapshotError::Parse(_))));
+        assert!(matches!(
+            load_snapshot(&temp_path("missing")),
+            Err(SnapshotError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+use nannou::prelude::{Rect, Vec2};
+
+// The most cells along the longest side of the grid
+const MAX_CELLS_PER_SIDE: f32 = 256.0;
+
+// A uniform grid over the world, bucketing the indices of points by cell, so that finding
+// the points near a position only has to look at a few cells instead of the whole flock
+#[derive(Default)]
+pub struct SpatialGrid {
+    origin: Vec2,
+    cell_size: f32,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(bounds: Rect, cell_size: f32, points: impl Iterator<Item = Vec2>) -> Self {
+        let mut grid = Self::default();
+        grid.rebuild(bounds, cell_size, points);
+        grid
+    }
+
+    // Buckets the points again, keeping the cells that were allocated, so a grid kept between
+    // steps doesn't have to allocate them every step
+    pub fn rebuild(&mut self, bounds: Rect, cell_size: f32, points: impl Iterator<Item = Vec2>) {
+        // Guard against tiny cells, which would give us millions of them, and zero sized cells,
+        // which would give us infinitely many
+        let cell_size = cell_size
+            .max(bounds.w().max(bounds.h()) / MAX_CELLS_PER_SIDE)
+            .max(1.0);
+        self.origin = bounds.bottom_left();
+        self.cell_size = cell_size;
+        self.cols = (bounds.w() / cell_size).ceil().max(1.0) as usize;
+        self.rows = (bounds.h() / cell_size).ceil().max(1.0) as usize;
+        self.cells.resize_with(self.cols * self.rows, Vec::new);
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+        for (index, point) in points.enumerate() {
+            let (col, row) = self.cell_of(point);
+            self.cells[row * self.cols + col].push(index);
+        }
+    }
+
+    // The column and row of the cell a point is in - points outside the grid go in the edge cells
+    fn cell_of(&self, point: Vec2) -> (usize, usize) {
+        let cell = (point - self.origin) / self.cell_size;
+        (
+            (cell.x.max(0.0) as usize).min(self.cols - 1),
+            (cell.y.max(0.0) as usize).min(self.rows - 1),
+        )
+    }
+
+    // The indices of all points in the cells touching the circle - the caller still has to check
+    // the actual distance
+    pub fn candidates(&self, position: Vec2, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let (min_col, min_row) = self.cell_of(position - Vec2::splat(radius));
+        let (max_col, max_row) = self.cell_of(position + Vec2::splat(radius));
+        (min_row..=max_row).flat_map(move |row| {
+            (min_col..=max_col)
+                .flat_map(move |col| self.cells[row * self.cols + col].iter().copied())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(points: &[Vec2]) -> SpatialGrid {
+        SpatialGrid::new(Rect::from_w_h(100.0, 100.0), 10.0, points.iter().copied())
+    }
+
+    #[test]
+    fn candidates_include_every_point_in_range() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(8.0, 3.0),
+            Vec2::new(-12.0, 0.0),
+            Vec2::new(40.0, 40.0),
+        ];
+        let grid = grid(&points);
+        let mut candidates: Vec<usize> = grid.candidates(Vec2::ZERO, 15.0).collect();
+        candidates.sort_unstable();
+        assert_eq!(candidates, [0, 1, 2]);
+    }
+
+    #[test]
+    fn candidates_leave_out_far_cells() {
+        let grid = grid(&[Vec2::new(-45.0, -45.0), Vec2::new(45.0, 45.0)]);
+        let candidates: Vec<usize> = grid.candidates(Vec2::new(40.0, 40.0), 5.0).collect();
+        assert_eq!(candidates, [1]);
+    }
+
+    #[test]
+    fn points_outside_the_grid_go_in_the_edge_cells() {
+        let grid = grid(&[Vec2::new(80.0, 0.0), Vec2::new(-80.0, -80.0)]);
+        let candidates: Vec<usize> = grid.candidates(Vec2::new(48.0, 0.0), 1.0).collect();
+        assert_eq!(candidates, [0]);
+        let candidates: Vec<usize> = grid.candidates(Vec2::new(-48.0, -48.0), 1.0).collect();
+        assert_eq!(candidates, [1]);
+    }
+
+    #[test]
+    fn tiny_cells_are_made_bigger() {
+        let grid = SpatialGrid::new(Rect::from_w_h(10000.0, 5000.0), 1.0, std::iter::empty());
+        assert_eq!(grid.cols, 256);
+        assert_eq!(grid.rows, 128);
+    }
+
+    #[test]
+    fn rebuilding_forgets_the_old_points() {
+        let mut grid = grid(&[Vec2::ZERO, Vec2::ONE]);
+        grid.rebuild(
+            Rect::from_w_h(50.0, 50.0),
+            10.0,
+            [Vec2::new(20.0, 20.0)].into_iter(),
+        );
+        assert_eq!(grid.candidates(Vec2::ZERO, 1.0).count(), 0);
+        assert_eq!(grid.candidates(Vec2::new(20.0, 20.0), 1.0).count(), 1);
+        assert_eq!(grid.cells.len(), 25);
+    }
+
+    #[test]
+    fn zero_sized_cells_still_make_a_grid() {
+        let grid = SpatialGrid::new(Rect::from_w_h(10.0, 10.0), 0.0, [Vec2::ZERO].into_iter());
+        assert_eq!(grid.candidates(Vec2::ZERO, 1.0).count(), 1);
+    }
+}
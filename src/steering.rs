@@ -0,0 +1,95 @@
+use clap::ValueEnum;
+use nannou::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+
+// The forces a boid steered with in its last step, kept around for inspecting it
+#[derive(PartialEq, Clone, Copy, Default)]
+pub struct Contributions {
+    pub alignment: Vec2,
+    pub cohesion: Vec2,
+    pub separation: Vec2,
+    pub foraging: Vec2,
+    pub trailing: Vec2,
+    pub fleeing: Vec2,
+    // The combined force, as it was added to the acceleration
+    pub total: Vec2,
+    // The number of boids within perception
+    pub neighbours: usize,
+    // How aligned the boid and its neighbours are, from 0.0 to 1.0
+    pub local_alignment: f32,
+}
+
+// How the steering forces of the different behaviours get combined into one
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum SteeringMode {
+    // Every force is added together, no matter how strong the sum gets
+    WeightedSum,
+    // Reynolds' prioritised acceleration allocation - the forces are taken in order of priority,
+    // until the max force of the boid is used up
+    Prioritised,
+}
+
+impl SteeringMode {
+    // Combines the forces, which have to be given in order of priority
+    pub fn combine(self, forces: &[Vec2], max_force: f32) -> Vec2 {
+        match self {
+            Self::WeightedSum => forces.iter().fold(Vec2::ZERO, |sum, &force| sum + force),
+            Self::Prioritised => {
+                let mut steering = Vec2::ZERO;
+                let mut budget = max_force;
+                for &force in forces {
+                    let magnitude = force.length();
+                    if magnitude >= budget {
+                        // Whatever is left of the budget goes to this force, and none to the rest
+                        steering += force.clamp_length_max(budget);
+                        break;
+                    }
+                    steering += force;
+                    budget -= magnitude;
+                }
+                steering
+            }
+        }
+    }
+
+    pub const fn toggled(self) -> Self {
+        match self {
+            Self::WeightedSum => Self::Prioritised,
+            Self::Prioritised => Self::WeightedSum,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::WeightedSum => "weighted sum",
+            Self::Prioritised => "prioritised",
+        }
+    }
+}
+
+// A behaviour the boids steer with, which can be left out from the command line
+#[derive(Serialize, Deserialize, ValueEnum, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    Alignment,
+    Cohesion,
+    Separation,
+    // Steering towards food
+    Foraging,
+    // Following the pheromone gradient
+    Trailing,
+    // Fleeing when alarmed
+    Fleeing,
+}
+
+impl Rule {
+    pub const ALL: [Self; 6] = [
+        Self::Alignment,
+        Self::Cohesion,
+        Self::Separation,
+        Self::Foraging,
+        Self::Trailing,
+        Self::Fleeing,
+    ];
+}
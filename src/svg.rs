@@ -0,0 +1,174 @@
+use crate::color::Color;
+use crate::export::ExportError;
+use crate::glyph::Shape;
+use crate::scene::Scene;
+use crate::trail::TRAIL_CAPACITY;
+
+use nannou::prelude::{Rect, Vec2};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+// Writes the flock as vector shapes, in the same colors and glyphs as the window shows them -
+// trails, if there is a tolerance, are simplified to fewer points within that many world units
+// of the real path, so pen plotters don't have to draw thousands of tiny lines
+pub fn write_svg(path: &Path, scene: &Scene, trails: Option<f32>) -> Result<(), ExportError> {
+    let bounds = scene.world.bounds;
+    let mut svg = String::new();
+    // Writing to a string can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = bounds.w(),
+        h = bounds.h(),
+    );
+
+    // Separate groups, so they can be plotted as separate layers
+    if let Some(tolerance) = trails {
+        svg.push_str("<g id=\"trails\" fill=\"none\" stroke-width=\"1\">\n");
+        for boid in scene.flock {
+            for segment in scene.trails.segments(boid, TRAIL_CAPACITY) {
+                let points = simplify(&segment, tolerance);
+                if points.len() >= 2 {
+                    let _ = writeln!(
+                        svg,
+                        r#"<polyline points="{}" stroke="{}"/>"#,
+                        points_attribute(&points, bounds),
+                        rgb(boid.color()),
+                    );
+                }
+            }
+        }
+        svg.push_str("</g>\n");
+    }
+
+    svg.push_str("<g id=\"boids\">\n");
+    for boid in scene.flock {
+        let color = boid.color();
+        for shape in boid.outline(scene.glyphs.of(boid.species())) {
+            let _ = match shape {
+                Shape::Fill(points) => writeln!(
+                    svg,
+                    r#"<polygon points="{}" fill="{}" fill-opacity="{}"/>"#,
+                    points_attribute(&points, bounds),
+                    rgb(color),
+                    color.a,
+                ),
+                Shape::Stroke(points) => writeln!(
+                    svg,
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-opacity="{}"/>"#,
+                    points_attribute(&points, bounds),
+                    rgb(color),
+                    color.a,
+                ),
+            };
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+
+    fs::write(path, svg)?;
+    Ok(())
+}
+
+// The points as an SVG attribute - SVG starts at the top left and goes down, the world starts
+// at the bottom left and goes up
+fn points_attribute(points: &[Vec2], bounds: Rect) -> String {
+    points
+        .iter()
+        .map(|point| {
+            format!(
+                "{:.2},{:.2}",
+                point.x - bounds.left(),
+                bounds.top() - point.y
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn rgb(color: Color) -> String {
+    let [r, g, b, _] = color.to_rgba8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// Ramer-Douglas-Peucker - keeps the point furthest from the line between the ends, if it is
+// further than the tolerance, and does the same for both halves
+fn simplify(points: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+        return Vec::new();
+    };
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let (index, distance) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(index, &point)| (index + 1, distance_to_line(point, first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("There are points between the ends");
+    if distance <= tolerance {
+        return vec![first, last];
+    }
+    let mut simplified = simplify(&points[..=index], tolerance);
+    // The point at the split is the end of the first half, and the start of the second
+    simplified.pop();
+    simplified.extend(simplify(&points[index..], tolerance));
+    simplified
+}
+
+// The distance from a point to the line through start and end
+fn distance_to_line(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let line = end - start;
+    if line.length() < f32::EPSILON {
+        return point.distance(start);
+    }
+    (line.perp_dot(point - start) / line.length()).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_drops_points_on_a_straight_line() {
+        let points: Vec<Vec2> = (0..10).map(|x| Vec2::new(x as f32, 0.0)).collect();
+        assert_eq!(
+            simplify(&points, 0.5),
+            [Vec2::new(0.0, 0.0), Vec2::new(9.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn simplify_keeps_corners_further_than_the_tolerance() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(5.0, 5.2),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(15.0, 5.2),
+            Vec2::new(20.0, 0.0),
+        ];
+        assert_eq!(simplify(&points, 1.0), [points[0], points[2], points[4]]);
+        assert_eq!(simplify(&points, 20.0), [points[0], points[4]]);
+    }
+
+    #[test]
+    fn simplify_leaves_short_paths_alone() {
+        assert!(simplify(&[], 1.0).is_empty());
+        let point = [Vec2::ONE];
+        assert_eq!(simplify(&point, 1.0), point);
+        let line = [Vec2::ZERO, Vec2::ONE];
+        assert_eq!(simplify(&line, 1.0), line);
+    }
+
+    #[test]
+    fn distance_to_a_line_through_one_point_is_to_the_point() {
+        assert_eq!(
+            distance_to_line(Vec2::new(3.0, 4.0), Vec2::ZERO, Vec2::ZERO),
+            5.0
+        );
+        assert_eq!(
+            distance_to_line(Vec2::new(3.0, 4.0), Vec2::ZERO, Vec2::X),
+            4.0
+        );
+    }
+}
@@ -1,25 +1,79 @@
+use crate::boid::Boid;
+use crate::ecology::POPULATION_HISTORY;
 use crate::model::Model;
 
 use nannou::prelude::{text, Draw, Rect, Vec2};
 
 const FONT_SIZE: u32 = 22;
 
-pub fn show_help_menu(draw: &Draw, win_rect: Rect) {
-    draw.text(
-        "\
-Keybinds:
+// The keybinds, shown in the window and in the terminal - a page at a time, so each one fits
+// in the default window and a small terminal
+pub const HELP_PAGES: [&str; 4] = [
+    "\
+What is shown:
  H - show this help menu
  J - sticky the help menu
+ U - show the next page of the help menu
+ Shift + U - show the previous page of the help menu
  C - show the current values
  V - sticky the current values
  S - highlight perception range of all boids
  D - sticky highlight perception range of all boids
  Z - highlight perception range of one boid
  X - sticky highlight perception range of one boid
+ Q - toggle drawing the velocity and the forces of the three rules
+ W - toggle trails
+ Shift + W - toggle not clearing the screen, for long exposures
+ , - shorten trails
+ . - lengthen trails
+ ; - fade trails
+ ' - brighten trails
+ F1 - toggle the density heatmap
+ F2 - toggle the occupancy map, of where the boids have been
+ Shift + F2 - clear the occupancy map",
+    "\
+The world:
  R - reset the simulation
  T - reset the position, velocity and acceleration, but nothing else
+ B - switch to the next arena
+ L - switch to the next preset
+ Shift + L - switch to the previous preset
+ K - toggle collisions between boids
+ E - toggle the ecology - energy, food, births and deaths
+ F - scatter food at random
+ Shift + F - remove all the food
+ Right click - place food
+ Y - toggle pheromone trails
+ Shift + Y - switch between following trails and avoiding them
+ P - switch between adding up and prioritising the steering forces
+ A - startle a random boid
+ Middle click - startle the boid under the mouse",
+    "\
+The camera, the looks and saving:
+ Scroll - zoom
+ Left drag - move the camera
+ O - fit the world in the window
+ I - toggle the camera following the selected boid
+ Left click - select a boid
+ M - switch to the next color scheme
+ Shift + M - switch to the next palette
+ G - switch to the next glyph
+ Shift + G - switch between one glyph for all boids, and one for each species
+ F3 - export the density and occupancy maps as PNG and CSV
+ F4 - start or stop recording
+ F5 - save the flock as an SVG
+ Shift + F5 - save the flock and its trails as an SVG
+ F6 - save the current values as a preset
+ Shift + F6 - save the current values as a config file
+ F7 - save a snapshot of everything to the current slot
+ F8 - load the snapshot in the current slot
+ F9 - switch to the next snapshot slot
+ Shift + F9 - switch to the previous snapshot slot",
+    "\
+The boids:
  - - remove a boid
  + - add a boid
+ N - add a boid of a random size
  [ - decrease perception range
  ] - increase perception range
  ↓ - shrink boids
@@ -33,39 +87,126 @@ Keybinds:
  7 - decrease cohesion modifier
  8 - increase cohesion modifier
  9 - decrease separation modifier
- 0 - increase separation modifier",
+ 0 - increase separation modifier
+ Shift + 5/6 - previous/next alignment falloff
+ Shift + 7/8 - previous/next cohesion falloff
+ Shift + 9/0 - previous/next separation falloff",
+];
+
+// A page of the keybinds, with which page it is
+pub fn help(page: usize) -> String {
+    let page = page % HELP_PAGES.len();
+    format!(
+        "Keybinds, page {} of {} - U for the next one\n{}",
+        page + 1,
+        HELP_PAGES.len(),
+        HELP_PAGES[page]
     )
-    .xy(win_rect.top_left())
-    .wh(Vec2::ZERO)
-    .no_line_wrap()
-    .justify(text::Justify::Left)
-    .align_text_top()
-    .font_size(FONT_SIZE)
-    .rgba(1.0, 1.0, 1.0, 0.5);
+}
+
+pub fn show_help_menu(draw: &Draw, win_rect: Rect, page: usize) {
+    draw.text(help(page).as_str())
+        .xy(win_rect.top_left())
+        .wh(Vec2::ZERO)
+        .no_line_wrap()
+        .justify(text::Justify::Left)
+        .align_text_top()
+        .font_size(FONT_SIZE)
+        .rgba(1.0, 1.0, 1.0, 0.5);
 }
 
 pub fn show_current_values(draw: &Draw, win_rect: Rect, model: &Model) {
-    let text = if let Some(first) = model.flock.first() {
+    draw.text(current_values(model).as_str())
+        .x_y(win_rect.left() + 5.0, win_rect.bottom() + 5.0)
+        .wh(Vec2::ZERO)
+        .no_line_wrap()
+        .justify(text::Justify::Left)
+        .align_text_bottom()
+        .font_size(FONT_SIZE)
+        .rgba(1.0, 1.0, 1.0, 0.5);
+}
+
+// The settings of the world, and the parameters of the first boid
+pub fn current_values(model: &Model) -> String {
+    if let Some(first) = model.flock.first() {
         format!(
             "\
 Current values:
+Preset: {}
+Snapshot slot: {}
+Steering: {}
+Colors: {} ({})
+Glyph: {}
+Arena: {}
 Number of boids: {}
 Perception radius: {}
 Diameter of boids: {}
+Mass of boids: {}
 Max speed: {}
 Max force: {}
-Alignment modifier: {}
-Cohesion modifier: {}
-Separation modifier: {}",
+Alignment modifier: {} ({} falloff)
+Cohesion modifier: {} ({} falloff)
+Separation modifier: {} ({} falloff)
+Foraging modifier: {}
+Pheromone modifier: {}
+Food eaten: {:.0}
+Collisions: {}
+Trails: {}
+Recording: {}
+Energy and age of first boid: {}",
             // The values to be put into the string
+            model
+                .preset_index
+                .and_then(|index| model.presets.get(index))
+                .map_or("none", |preset| preset.name.as_str()),
+            model.snapshot_slot + 1,
+            model.world.steering.name(),
+            model.world.coloring.scheme.name(),
+            model.world.coloring.palette.name(),
+            if model.glyphs.per_species {
+                "one per species"
+            } else {
+                model.glyphs.glyph.name()
+            },
+            model.world.arena.name(),
             model.flock.len(),
             first.perception_radius(),
             first.diameter(),
+            first.mass(),
             first.max_speed(),
             first.max_force(),
             first.alignment_modifier(),
+            first.alignment_falloff().name(),
             first.cohesion_modifier(),
+            first.cohesion_falloff().name(),
             first.separation_modifier(),
+            first.separation_falloff().name(),
+            first.foraging_modifier(),
+            first.pheromone_modifier(),
+            model.world.foraging.eaten,
+            if model.world.collisions {
+                model.world.collision_count.to_string()
+            } else {
+                String::from("off")
+            },
+            if model.trails.enabled {
+                format!(
+                    "{} long, {:.2} alpha",
+                    model.trails.length, model.trails.alpha
+                )
+            } else {
+                String::from("off")
+            },
+            if model.recorder.recording {
+                format!("{} frames", model.recorder.captured())
+            } else {
+                String::from("off")
+            },
+            if model.world.ecology.enabled {
+                format!("{:.1}, {}", first.energy(), first.age())
+            } else {
+                String::from("off")
+            },
         )
     } else {
         String::from(
@@ -74,9 +215,38 @@ Separation modifier: {}",
   There are no boids :(
             ",
         )
+    }
+}
+
+// The size of the population plot
+const PLOT_WIDTH: f32 = 300.0;
+const PLOT_HEIGHT: f32 = 120.0;
+
+// Plots the size of the population over time, in the bottom right corner
+pub fn show_population_plot(draw: &Draw, win_rect: Rect, model: &Model) {
+    let history = &model.world.ecology.population_history;
+    let Some(&largest) = history.iter().max() else {
+        return;
     };
-    draw.text(text.as_str())
-        .x_y(win_rect.left() + 5.0, win_rect.bottom() + 5.0)
+    // The bottom left corner of the plot
+    let origin = Vec2::new(win_rect.right() - PLOT_WIDTH - 5.0, win_rect.bottom() + 5.0);
+    draw.rect()
+        .xy(origin + Vec2::new(PLOT_WIDTH, PLOT_HEIGHT) / 2.0)
+        .w_h(PLOT_WIDTH, PLOT_HEIGHT)
+        .rgba(1.0, 1.0, 1.0, 0.05);
+    let points = history.iter().enumerate().map(|(i, &population)| {
+        origin
+            + Vec2::new(
+                i as f32 / POPULATION_HISTORY as f32 * PLOT_WIDTH,
+                population as f32 / largest.max(1) as f32 * PLOT_HEIGHT,
+            )
+    });
+    draw.polyline()
+        .weight(1.5)
+        .points(points)
+        .rgba(1.0, 1.0, 1.0, 0.5);
+    draw.text(format!("Population: {}", model.flock.len()).as_str())
+        .xy(origin + Vec2::new(5.0, PLOT_HEIGHT + 5.0))
         .wh(Vec2::ZERO)
         .no_line_wrap()
         .justify(text::Justify::Left)
@@ -84,3 +254,103 @@ Separation modifier: {}",
         .font_size(FONT_SIZE)
         .rgba(1.0, 1.0, 1.0, 0.5);
 }
+
+// Shows the live state of a boid, in the top right corner
+pub fn show_inspector(draw: &Draw, win_rect: Rect, boid: &Boid) {
+    let contributions = boid.contributions();
+    let text = format!(
+        "\
+Selected boid: {}
+Species: {}
+Position: ({:.1}, {:.1})
+Velocity: ({:.2}, {:.2})
+Acceleration: ({:.4}, {:.4})
+  Alignment: ({:.4}, {:.4})
+  Cohesion: ({:.4}, {:.4})
+  Separation: ({:.4}, {:.4})
+  Foraging: ({:.4}, {:.4})
+  Pheromones: ({:.4}, {:.4})
+  Fleeing: ({:.4}, {:.4})
+Neighbours: {} ({:.2} aligned)
+Perception radius: {}
+Diameter: {}
+Mass: {}
+Max speed: {}
+Max force: {}
+Alignment modifier: {}
+Cohesion modifier: {}
+Separation modifier: {}
+Energy: {:.1}",
+        boid.id(),
+        boid.species(),
+        boid.position().x,
+        boid.position().y,
+        boid.velocity().x,
+        boid.velocity().y,
+        // The acceleration is reset every step, so show the force it came from, divided by the mass
+        contributions.total.x / boid.mass(),
+        contributions.total.y / boid.mass(),
+        contributions.alignment.x,
+        contributions.alignment.y,
+        contributions.cohesion.x,
+        contributions.cohesion.y,
+        contributions.separation.x,
+        contributions.separation.y,
+        contributions.foraging.x,
+        contributions.foraging.y,
+        contributions.trailing.x,
+        contributions.trailing.y,
+        contributions.fleeing.x,
+        contributions.fleeing.y,
+        contributions.neighbours,
+        contributions.local_alignment,
+        boid.perception_radius(),
+        boid.diameter(),
+        boid.mass(),
+        boid.max_speed(),
+        boid.max_force(),
+        boid.alignment_modifier(),
+        boid.cohesion_modifier(),
+        boid.separation_modifier(),
+        boid.energy(),
+    );
+    draw.text(text.as_str())
+        .xy(win_rect.top_right() - Vec2::new(5.0, 5.0))
+        .wh(Vec2::ZERO)
+        .no_line_wrap()
+        .justify(text::Justify::Right)
+        .align_text_top()
+        .font_size(FONT_SIZE)
+        .rgba(1.0, 1.0, 1.0, 0.5);
+}
+
+// Shows a message at the top of the window, like a broken config file, or where a file was saved
+pub fn show_message(draw: &Draw, win_rect: Rect, message: &str) {
+    draw.text(message)
+        .xy(win_rect.mid_top() - Vec2::new(0.0, 5.0))
+        .wh(Vec2::ZERO)
+        .no_line_wrap()
+        .center_justify()
+        .align_text_top()
+        .font_size(FONT_SIZE)
+        .rgba(1.0, 0.85, 0.4, 0.9);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn help_pages_fit_in_the_default_window_and_a_small_terminal() {
+        // The default window is 768 pixels tall, and a small terminal has 22 rows inside its border
+        for page in 0..HELP_PAGES.len() {
+            assert!(help(page).lines().count() <= 22);
+        }
+    }
+
+    #[test]
+    fn help_pages_go_round() {
+        assert_eq!(help(HELP_PAGES.len()), help(0));
+        assert!(help(1).starts_with("Keybinds, page 2 of 4"));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::boid::Boid;
+
+use nannou::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+// The most positions a trail can remember
+pub const TRAIL_CAPACITY: usize = 128;
+// Trails are broken up where a boid moved further than this many times its max speed in one step
+const TRAIL_JUMP: f32 = 10.0;
+
+// How the trails are drawn
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Trails {
+    pub enabled: bool,
+    // The number of positions drawn, at most TRAIL_CAPACITY
+    pub length: usize,
+    // The alpha of the newest part of the trail, which fades to nothing at the oldest part
+    pub alpha: f32,
+    // Don't clear the background, so everything leaves a mark - like a long exposure
+    pub accumulate: bool,
+    // Remember where the boids have been even while the trails aren't drawn, for exports
+    #[serde(default)]
+    pub record: bool,
+    // The trail of every boid, by its id - kept out of the boids, so they stay small, and sorted,
+    // so snapshots of the same flock are the same
+    #[serde(default)]
+    paths: BTreeMap<u64, Trail>,
+}
+
+impl Default for Trails {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            length: 32,
+            alpha: 0.5,
+            accumulate: false,
+            record: false,
+            paths: BTreeMap::new(),
+        }
+    }
+}
+
+impl Trails {
+    // Adds where every boid is now to its trail, if trails are drawn or recorded
+    pub fn push(&mut self, flock: &[Boid]) {
+        if !self.enabled && !self.record {
+            return;
+        }
+        for boid in flock {
+            self.paths
+                .entry(boid.id())
+                .or_default()
+                .push(boid.position());
+        }
+        // Forget the boids that died
+        if self.paths.len() > flock.len() {
+            let alive: HashSet<u64> = flock.iter().map(Boid::id).collect();
+            self.paths.retain(|id, _| alive.contains(id));
+        }
+    }
+
+    // Forgets where every boid has been
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    // The newest positions of a boid, split wherever it wrapped around the world
+    pub fn segments(&self, boid: &Boid, length: usize) -> Vec<Vec<Vec2>> {
+        self.paths.get(&boid.id()).map_or_else(Vec::new, |trail| {
+            trail.segments(length, boid.max_speed() * TRAIL_JUMP)
+        })
+    }
+}
+
+// The recent positions of a boid, in a ring buffer - saved as a list of them, from the oldest
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(from = "Vec<Vec2>", into = "Vec<Vec2>")]
+struct Trail {
+    points: [Vec2; TRAIL_CAPACITY],
+    // Where the next position goes
+    head: usize,
+    len: usize,
+}
+
+impl Trail {
+    fn push(&mut self, point: Vec2) {
+        self.points[self.head] = point;
+        self.head = (self.head + 1) % TRAIL_CAPACITY;
+        self.len = (self.len + 1).min(TRAIL_CAPACITY);
+    }
+
+    // The newest positions, at most `length` of them, from the oldest to the newest
+    fn recent(&self, length: usize) -> impl Iterator<Item = Vec2> + '_ {
+        let length = length.min(self.len);
+        (0..length)
+            .map(move |i| self.points[(self.head + TRAIL_CAPACITY - length + i) % TRAIL_CAPACITY])
+    }
+
+    // The newest positions split into pieces wherever the boid jumped further than `max_jump`,
+    // so wrapping around the edges doesn't draw a line across the whole world
+    fn segments(&self, length: usize, max_jump: f32) -> Vec<Vec<Vec2>> {
+        let mut segments: Vec<Vec<Vec2>> = Vec::new();
+        let mut last: Option<Vec2> = None;
+        for point in self.recent(length) {
+            match (last, segments.last_mut()) {
+                (Some(last), Some(segment)) if last.distance(point) <= max_jump => {
+                    segment.push(point);
+                }
+                _ => segments.push(vec![point]),
+            }
+            last = Some(point);
+        }
+        segments
+    }
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Self {
+            points: [Vec2::ZERO; TRAIL_CAPACITY],
+            head: 0,
+            len: 0,
+        }
+    }
+}
+
+impl From<Vec<Vec2>> for Trail {
+    fn from(points: Vec<Vec2>) -> Self {
+        let mut trail = Self::default();
+        for point in points {
+            trail.push(point);
+        }
+        trail
+    }
+}
+
+impl From<Trail> for Vec<Vec2> {
+    fn from(trail: Trail) -> Self {
+        trail.recent(TRAIL_CAPACITY).collect()
+    }
+}
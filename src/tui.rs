@@ -0,0 +1,224 @@
+use crate::cli::TuiArgs;
+use crate::keys::{press, release, DIGITS, LETTERS};
+use crate::model::Model;
+use crate::text::{current_values, help};
+use crate::update::step;
+
+use clap::ValueEnum;
+use nannou::prelude::Key;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Circle, Line, Points};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::time::{Duration, Instant};
+
+// How long each step is shown for, at most
+const FRAME_TIME: Duration = Duration::from_millis(33);
+// The width of the panels with the help menu and the current values, in characters
+const PANEL_WIDTH: u16 = 64;
+
+// How the flock is drawn in the terminal
+#[derive(ValueEnum, PartialEq, Clone, Copy)]
+pub enum TuiMarker {
+    // Eight dots per character, in one color
+    Braille,
+    // Two pixels per character, with their own colors
+    HalfBlock,
+}
+
+// Runs the simulation in the terminal, until escape is pressed
+pub fn run(args: &TuiArgs) -> io::Result<()> {
+    // The model is made before the terminal is taken over, so what goes wrong loading it can
+    // still be read
+    let model = Model::without_window();
+    let mut terminal = ratatui::init();
+    let result = run_loop(&mut terminal, model, args);
+    ratatui::restore();
+    result
+}
+
+fn run_loop(terminal: &mut DefaultTerminal, mut model: Model, args: &TuiArgs) -> io::Result<()> {
+    let marker = match args.marker {
+        TuiMarker::Braille => Marker::Braille,
+        TuiMarker::HalfBlock => Marker::HalfBlock,
+    };
+
+    loop {
+        step(&mut model);
+        terminal.draw(|frame| draw(frame, &model, marker))?;
+
+        // Handle every key that comes in until it is time for the next step
+        let next_step = Instant::now() + FRAME_TIME;
+        while event::poll(next_step.saturating_duration_since(Instant::now()))? {
+            let Event::Key(event) = event::read()? else {
+                continue;
+            };
+            if event.kind != KeyEventKind::Press {
+                continue;
+            }
+            // Escape quits, like it does in the window
+            let control = event.modifiers.contains(KeyModifiers::CONTROL);
+            if event.code == KeyCode::Esc || (control && event.code == KeyCode::Char('c')) {
+                return Ok(());
+            }
+            // Terminals don't say when keys are let go, so every key is let go straight away -
+            // the keys that only work while held do nothing here, but their sticky versions do
+            if let Some((key, shift)) = to_key(event) {
+                press(&mut model, key, shift);
+                release(&mut model, key);
+            }
+        }
+    }
+}
+
+// The nannou key for a terminal key, and whether shift was held
+fn to_key(event: KeyEvent) -> Option<(Key, bool)> {
+    let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+    let key = match event.code {
+        KeyCode::Char(c) if c.is_ascii_lowercase() => LETTERS[(c as u8 - b'a') as usize],
+        KeyCode::Char(c) if c.is_ascii_uppercase() => {
+            return Some((LETTERS[(c as u8 - b'A') as usize], true));
+        }
+        KeyCode::Char(c) if c.is_ascii_digit() => DIGITS[(c as u8 - b'0') as usize],
+        // Shift and a digit gives a symbol in the terminal
+        KeyCode::Char(c) => match c {
+            ')' | '!' | '@' | '#' | '$' | '%' | '^' | '&' | '*' | '(' => {
+                let digit = ")!@#$%^&*(".find(c)?;
+                return Some((DIGITS[digit], true));
+            }
+            '+' => Key::Plus,
+            '-' => Key::Minus,
+            '[' => Key::LBracket,
+            ']' => Key::RBracket,
+            ',' => Key::Comma,
+            '.' => Key::Period,
+            ';' => Key::Semicolon,
+            '\'' => Key::Apostrophe,
+            _ => return None,
+        },
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::F(1) => Key::F1,
+        KeyCode::F(2) => Key::F2,
+        KeyCode::F(3) => Key::F3,
+        KeyCode::F(4) => Key::F4,
+        KeyCode::F(5) => Key::F5,
+        KeyCode::F(6) => Key::F6,
+        KeyCode::F(7) => Key::F7,
+        KeyCode::F(8) => Key::F8,
+        KeyCode::F(9) => Key::F9,
+        _ => return None,
+    };
+    Some((key, shift))
+}
+
+// Draws the flock, with the help menu and the current values next to it when they are shown
+fn draw(frame: &mut Frame, model: &Model, marker: Marker) {
+    let show_panel = model.keybinds.show_help_menu || model.keybinds.show_current_values;
+    let [world_area, panel_area] = Layout::horizontal([
+        Constraint::Min(0),
+        Constraint::Length(if show_panel { PANEL_WIDTH } else { 0 }),
+    ])
+    .areas(frame.area());
+    // What went wrong with reloading the config file, and what just happened, go under the flock
+    let message = model.message();
+    let [world_area, message_area] = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(
+            message
+                .as_ref()
+                .map_or(0, |message| message.lines().count() as u16 + 2),
+        ),
+    ])
+    .areas(world_area);
+
+    let bounds = model.world.bounds;
+    let title = format!(" {} boids! ", model.flock.len());
+    let canvas = Canvas::default()
+        .block(Block::bordered().title(title))
+        .marker(marker)
+        .x_bounds([f64::from(bounds.left()), f64::from(bounds.right())])
+        .y_bounds([f64::from(bounds.bottom()), f64::from(bounds.top())])
+        .paint(|context| {
+            // The walls of the arena
+            let walls = model.world.arena.outline(bounds).unwrap_or_default();
+            for (start, end) in walls.iter().zip(walls.iter().cycle().skip(1)) {
+                context.draw(&Line::new(
+                    f64::from(start.x),
+                    f64::from(start.y),
+                    f64::from(end.x),
+                    f64::from(end.y),
+                    Color::DarkGray,
+                ));
+            }
+            for food in &model.world.foraging.food {
+                context.draw(&Circle {
+                    x: f64::from(food.position.x),
+                    y: f64::from(food.position.y),
+                    radius: f64::from(food.radius),
+                    color: Color::Green,
+                });
+            }
+            if model.keybinds.highlight_first {
+                if let Some(boid) = model.flock.first() {
+                    context.draw(&Circle {
+                        x: f64::from(boid.position().x),
+                        y: f64::from(boid.position().y),
+                        radius: f64::from(boid.perception_radius()),
+                        color: Color::White,
+                    });
+                }
+            }
+            context.layer();
+            for boid in &model.flock {
+                let [r, g, b, _] = boid.color().to_rgba8();
+                let position = boid.position();
+                context.draw(&Points {
+                    coords: &[(f64::from(position.x), f64::from(position.y))],
+                    color: Color::Rgb(r, g, b),
+                });
+            }
+        });
+    frame.render_widget(canvas, world_area);
+    if let Some(message) = message {
+        frame.render_widget(
+            Paragraph::new(message)
+                .style(Style::default().fg(Color::LightYellow))
+                .block(Block::bordered()),
+            message_area,
+        );
+    }
+
+    if show_panel {
+        let [values_area, help_area] = Layout::vertical([
+            Constraint::Length(if model.keybinds.show_current_values {
+                current_values(model).lines().count() as u16 + 2
+            } else {
+                0
+            }),
+            Constraint::Min(0),
+        ])
+        .areas(panel_area);
+        let style = Style::default().fg(Color::Gray);
+        if model.keybinds.show_current_values {
+            frame.render_widget(
+                Paragraph::new(current_values(model))
+                    .style(style)
+                    .block(Block::bordered()),
+                values_area,
+            );
+        }
+        if model.keybinds.show_help_menu {
+            frame.render_widget(
+                Paragraph::new(help(model.keybinds.help_page))
+                    .style(style)
+                    .block(Block::bordered()),
+                help_area,
+            );
+        }
+    }
+}
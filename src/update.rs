@@ -1,48 +1,177 @@
+use crate::boid::Boid;
+use crate::color::BACKGROUND;
+use crate::config::ConfigWatcher;
 use crate::model::Model;
-use crate::text::{show_current_values, show_help_menu};
+use crate::scene::Scene;
+use crate::text::{
+    show_current_values, show_help_menu, show_inspector, show_message, show_population_plot,
+};
 
 use nannou::prelude::{App, Frame, Update};
-use rayon::prelude::*;
 
 // Update the state of our application every frame
-pub fn update(_app: &App, model: &mut Model, _update: Update) {
-    // Create a temp flock, to ensure thread safety, so that the actual flock
-    // is not getting modified *and* compared to at the same time
-    let temp_flock = model.flock.clone();
-    model
-        .flock
-        .par_iter_mut()
-        .for_each(|boid| boid.flock(&temp_flock, model.win_rect));
+pub fn update(app: &App, model: &mut Model, _update: Update) {
+    let population = model.flock.len();
+    step(model);
+    // Boids can be born or die when the ecology is enabled
+    if model.flock.len() != population {
+        app.main_window()
+            .set_title(format!("{} boids!", model.flock.len()).as_str());
+    }
+}
+
+// Moves the model one step forward, whether it is shown in the window or the terminal
+pub fn step(model: &mut Model) {
+    // Pick up changes to the config file, keeping the boids where they are
+    if let Some((config, previous)) = model.watcher.as_mut().and_then(ConfigWatcher::poll) {
+        config.apply_live(&previous, model);
+    }
+
+    model.world.step(&mut model.flock);
+    model.trails.push(&model.flock);
+
+    // Record the step, stopping once there are enough frames, or if a frame can't be saved
+    if model.recorder.recording {
+        // Borrowing the fields one by one, since the recorder changes while the scene is drawn
+        let scene = Scene {
+            world: &model.world,
+            flock: &model.flock,
+            trails: &model.trails,
+            glyphs: &model.glyphs,
+            keybinds: &model.keybinds,
+            selected: model.selected,
+        };
+        // The recording shows what the window does
+        let camera = model
+            .camera
+            .resized(model.win_rect, model.recorder.frame_rect());
+        match model.recorder.capture(&scene, &camera) {
+            Ok(false) => {}
+            Ok(true) => model.recorder.stop(),
+            Err(error) => {
+                model.say(format!("Unable to record: {error}"));
+                model.recorder.stop();
+            }
+        }
+    }
+
+    // Say how the last recording was saved, once it has been
+    match model.recorder.finished() {
+        Some(Ok(saved)) => model.say(saved),
+        Some(Err(error)) => model.say(format!("Unable to save the recording: {error}")),
+        None => {}
+    }
+
+    // Keep the selected boid in the centre of the window
+    if model.keybinds.follow_selected {
+        if let Some(position) = model.selected_boid().map(Boid::position) {
+            model.camera.position = position;
+        }
+    }
 }
 
 // Draw our stuff to the screen every frame
 pub fn view(app: &App, model: &Model, frame: Frame) {
-    // Draw background
-    model.draw.background().rgb(0.1569, 0.1569, 0.1569);
+    // The draw is kept between frames, and would keep the background of the last one, which
+    // clears the frame even when it shouldn't
+    model.draw.reset();
+
+    // Draw background - unless everything should pile up, like a long exposure
+    if !model.trails.accumulate {
+        model
+            .draw
+            .background()
+            .rgb(BACKGROUND.r, BACKGROUND.g, BACKGROUND.b);
+    }
+
+    // Everything in the world is drawn through the camera, and the text straight to the window
+    let draw = model.camera.transform(&model.draw);
+
+    // Draw the pheromones below everything else
+    if model.world.pheromones.enabled {
+        model.world.pheromones.show(&draw);
+    }
+
+    // Draw where the boids are, and where they have been, above the pheromones
+    if model.world.density.show_occupancy {
+        model
+            .world
+            .density
+            .show_occupancy(&draw, model.world.coloring.palette);
+    }
+    if model.world.density.show_density {
+        model
+            .world
+            .density
+            .show_density(&draw, model.world.coloring.palette);
+    }
+
+    // Draw the walls of the arena
+    model.world.arena.show(&draw, model.world.bounds);
+
+    // Draw the food below the boids
+    for food in &model.world.foraging.food {
+        food.show(&draw);
+    }
 
     // Only highlight the first boid, if it exists
     if model.keybinds.highlight_first {
         if let Some(boid) = model.flock.first() {
-            boid.show_perception(&model.draw, 0.025);
+            boid.show_perception(&draw, 0.025);
         }
     }
 
     // Show all the boids
     for boid in &model.flock {
-        boid.show(&model.draw);
+        boid.show(&draw, &model.trails, &model.glyphs);
         if model.keybinds.highlight_all {
-            boid.show_perception(&model.draw, 0.0025);
+            boid.show_perception(&draw, 0.0025);
+        }
+    }
+
+    // Show the forces steering every boid
+    if model.keybinds.show_forces {
+        for boid in &model.flock {
+            boid.show_forces(&draw);
         }
     }
 
+    // Show what the selected boid can see, and the boids it counts as neighbours
+    let selected = model.selected_boid();
+    if let Some(selected) = selected {
+        selected.show_perception(&draw, 0.05);
+        if model.keybinds.show_forces {
+            selected.show_neighbour_lines(&draw, &model.flock);
+        }
+        for other in &model.flock {
+            if selected.is_neighbour(other) {
+                other.show_highlight(&draw);
+            }
+        }
+        selected.show_highlight(&draw);
+    }
+
     // Draw the help menu
     if model.keybinds.show_help_menu {
-        show_help_menu(&model.draw, model.win_rect);
+        show_help_menu(&model.draw, model.win_rect, model.keybinds.help_page);
     }
 
     // Draw the current values
     if model.keybinds.show_current_values {
         show_current_values(&model.draw, model.win_rect, model);
+        if model.world.ecology.enabled {
+            show_population_plot(&model.draw, model.win_rect, model);
+        }
+    }
+
+    // Draw the live state of the selected boid
+    if let Some(selected) = selected {
+        show_inspector(&model.draw, model.win_rect, selected);
+    }
+
+    // Say what went wrong with reloading the config file, and what just happened
+    if let Some(message) = model.message() {
+        show_message(&model.draw, model.win_rect, &message);
     }
 
     // Push stuff to screen
@@ -1,7 +1,82 @@
+use crate::camera::Drag;
 use crate::model::Model;
 
-use nannou::prelude::{App, Vec2};
+use nannou::event::{MouseScrollDelta, TouchPhase};
+use nannou::prelude::{App, MouseButton, Point2, Vec2};
+
+// How much one line of scrolling zooms
+const ZOOM_PER_LINE: f32 = 1.1;
+// The number of pixels of scrolling that count as one line
+const PIXELS_PER_LINE: f32 = 40.0;
+// Drags shorter than this, in pixels, count as clicks
+const CLICK_DISTANCE: f32 = 4.0;
+// How close to a boid a click has to be to select it, in pixels
+const SELECT_DISTANCE: f32 = 12.0;
 
 pub fn resized(app: &App, model: &mut Model, _dim: Vec2) {
+    // Only the visible part changes - the world stays the same size
     model.win_rect = app.window_rect();
 }
+
+pub fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    let position = model.camera.to_world(app.mouse.position());
+    match button {
+        // Start dragging the camera around - or selecting a boid, if the mouse doesn't move
+        MouseButton::Left => {
+            model.drag = Some(Drag {
+                start: app.mouse.position(),
+                last: app.mouse.position(),
+            });
+        }
+        // Place a patch of food under the mouse
+        MouseButton::Right => model.world.foraging.place(position),
+        // Startle the boid under the mouse, sending a wave of alarm through the flock
+        MouseButton::Middle => model.world.startle_near(&mut model.flock, position),
+        _ => (),
+    }
+}
+
+pub fn mouse_released(app: &App, model: &mut Model, button: MouseButton) {
+    if button != MouseButton::Left {
+        return;
+    }
+    if let Some(drag) = model.drag.take() {
+        if drag.start.distance(app.mouse.position()) < CLICK_DISTANCE {
+            select_boid(model, model.camera.to_world(drag.start));
+        }
+    }
+}
+
+pub fn mouse_moved(_app: &App, model: &mut Model, position: Point2) {
+    if let Some(drag) = &mut model.drag {
+        model.camera.pan(position - drag.last);
+        drag.last = position;
+        // Moving the camera by hand stops it from following the selected boid
+        if drag.start.distance(position) >= CLICK_DISTANCE {
+            model.keybinds.follow_selected = false;
+        }
+    }
+}
+
+// Selects the boid closest to a point in the world, or nothing if there is no boid close enough
+fn select_boid(model: &mut Model, position: Vec2) {
+    model.selected = model
+        .flock
+        .iter()
+        .map(|boid| (boid, boid.position().distance(position)))
+        .filter(|&(boid, distance)| {
+            distance < boid.diameter().max(SELECT_DISTANCE / model.camera.zoom)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(boid, _)| boid.id());
+}
+
+pub fn mouse_wheel(app: &App, model: &mut Model, delta: MouseScrollDelta, _phase: TouchPhase) {
+    let lines = match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+    };
+    model
+        .camera
+        .zoom_at(app.mouse.position(), ZOOM_PER_LINE.powf(lines));
+}
@@ -0,0 +1,164 @@
+use crate::alarm::Alarms;
+use crate::arena::{Arena, Boundary};
+use crate::boid::{Boid, Parameters};
+use crate::cluster::label_clusters;
+use crate::collision::resolve_collisions;
+use crate::color::{ColorScheme, Coloring};
+use crate::density::DensityMap;
+use crate::ecology::Ecology;
+use crate::food::Foraging;
+use crate::pheromone::PheromoneGrid;
+use crate::spatial::SpatialGrid;
+use crate::steering::{Rule, SteeringMode};
+
+use nannou::prelude::{Rect, Vec2};
+use rayon::prelude::*;
+
+// The state of the simulation, apart from the boids themselves
+pub struct World {
+    pub bounds: Rect,
+    // The part of the bounds the boids can fly in
+    pub arena: Arena,
+    // Whether the boids wrap around the edges of the rectangle arena, or bounce off them
+    pub boundary: Boundary,
+    // How the boids combine the forces of their behaviours
+    pub steering: SteeringMode,
+    // The behaviours the boids steer with - the rest are left out
+    pub rules: Vec<Rule>,
+    // Whether overlapping boids get pushed apart after moving
+    pub collisions: bool,
+    // The number of overlapping pairs found in the last step
+    pub collision_count: usize,
+    // The grid the collisions are found with, kept between steps
+    collision_grid: SpatialGrid,
+    pub ecology: Ecology,
+    pub foraging: Foraging,
+    pub pheromones: PheromoneGrid,
+    pub alarms: Alarms,
+    // Where the boids are, and where they have been
+    pub density: DensityMap,
+    // What the colors of the boids show
+    pub coloring: Coloring,
+    // The number of species new flocks are split into
+    pub species: u8,
+    // The parameters new flocks start with
+    pub parameters: Parameters,
+}
+
+impl World {
+    pub fn new(bounds: Rect) -> Self {
+        Self {
+            bounds,
+            arena: Arena::Rect,
+            boundary: Boundary::Wrap,
+            steering: SteeringMode::WeightedSum,
+            rules: Rule::ALL.to_vec(),
+            collisions: false,
+            collision_count: 0,
+            collision_grid: SpatialGrid::default(),
+            ecology: Ecology::default(),
+            foraging: Foraging::default(),
+            pheromones: PheromoneGrid::new(bounds),
+            alarms: Alarms::default(),
+            density: DensityMap::new(bounds),
+            coloring: Coloring::default(),
+            species: 1,
+            parameters: Parameters::default(),
+        }
+    }
+
+    // Whether boids leaving the world come back on the other side
+    pub fn wraps(&self) -> bool {
+        self.arena.wraps() && self.boundary == Boundary::Wrap
+    }
+
+    // Whether the boids steer with a behaviour
+    pub fn follows(&self, rule: Rule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    // A random position inside the arena
+    pub fn random_position(&self) -> Vec2 {
+        self.arena.random_point(self.bounds)
+    }
+
+    // Switches to another arena, moving the boids that end up in a wall to somewhere inside
+    pub fn change_arena(&mut self, arena: Arena, flock: &mut [Boid]) {
+        self.arena = arena;
+        for boid in flock {
+            if !self.arena.contains(boid.position(), self.bounds) {
+                boid.change_position(self.random_position());
+            }
+        }
+        let food = std::mem::take(&mut self.foraging.food);
+        self.foraging.food = food
+            .into_iter()
+            .filter(|food| self.arena.contains(food.position, self.bounds))
+            .collect();
+    }
+
+    // Alarms the boid closest to a position, making it flee away from there
+    pub fn startle_near(&mut self, flock: &mut [Boid], position: Vec2) {
+        let closest = flock.iter_mut().min_by(|a, b| {
+            a.position()
+                .distance(position)
+                .total_cmp(&b.position().distance(position))
+        });
+        if let Some(boid) = closest {
+            let mut heading = boid.position() - position;
+            // Something right on top of the boid scares it straight back
+            if heading.length() < f32::EPSILON {
+                heading = -boid.velocity();
+            }
+            self.startle(boid, heading);
+        }
+    }
+
+    // Alarms a boid, starting a new wave of alarm
+    pub fn startle(&mut self, boid: &mut Boid, heading: Vec2) {
+        boid.startle(self.alarms.next_id(), heading);
+    }
+
+    // Moves the simulation one step forward
+    pub fn step(&mut self, flock: &mut Vec<Boid>) {
+        // The bounds of the world can change, like when a snapshot is loaded, so the grids are
+        // fitted to them again
+        self.pheromones.fit(self.bounds);
+        self.density.fit(self.bounds);
+
+        // Finding the clusters is too slow to do when they aren't shown
+        if self.coloring.scheme == ColorScheme::Cluster {
+            label_clusters(flock, self.bounds);
+        }
+
+        // Create a temp flock, to ensure thread safety, so that the actual flock
+        // is not getting modified *and* compared to at the same time
+        let temp_flock = flock.to_vec();
+        flock
+            .par_iter_mut()
+            .for_each(|boid| boid.flock(&temp_flock, self));
+
+        self.collision_count = if self.collisions {
+            // Taken out of the world while the collisions are resolved, since they look at the
+            // rest of it
+            let mut grid = std::mem::take(&mut self.collision_grid);
+            let count = resolve_collisions(flock, self, &mut grid);
+            self.collision_grid = grid;
+            count
+        } else {
+            0
+        };
+
+        if self.pheromones.enabled {
+            self.pheromones.step(flock);
+        }
+
+        // Boids only gain energy from eating when the ecology is enabled
+        self.foraging.step(flock, self.ecology.enabled);
+
+        // Births and deaths happen last, once every boid has moved
+        self.ecology.step(flock);
+
+        self.density.step(flock);
+    }
+}